@@ -0,0 +1,42 @@
+//! Benchmark one training step of the evolution trainer, covering population
+//! generation, breeding and mutation.
+#![allow(missing_docs, clippy::missing_docs_in_private_items)]
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use players::{AiValuePlayer, NdArrayBackend};
+use train::EvolutionTrainer;
+
+criterion_main!(benches);
+criterion_group!(benches, evolution_train_step_benchmark);
+
+/// Deterministic evaluator, scoring the models by their position in the
+/// population.
+fn index_scores(models: &[AiValuePlayer<NdArrayBackend>]) -> Vec<f32> {
+	(0..models.len()).map(|i| i as f32).collect()
+}
+
+fn evolution_train_step_benchmark(c: &mut Criterion) {
+	c.bench_function("evolution_train_step", move |b| {
+		b.iter_batched(
+			|| {
+				let population = (0..5).map(|_| AiValuePlayer::init(1)).collect();
+				EvolutionTrainer::builder()
+					.population(population)
+					.init_fn(Box::new(|| AiValuePlayer::init(1)))
+					.evaluator(index_scores)
+					.population_max(20)
+					.population_min(5)
+					.generate_new(0.0)
+					.mutation_probability(1.0)
+					.mutation_std(0.005)
+					.build()
+					.with_seed(42)
+			},
+			|mut trainer| {
+				trainer.train_step();
+				trainer
+			},
+			BatchSize::SmallInput,
+		);
+	});
+}
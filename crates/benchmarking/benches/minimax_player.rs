@@ -1,13 +1,27 @@
 //! Benchmark a game with minimax players, benchmarking the minimax player
-//! performance.
+//! performance. Also benchmark single moves of the minimax player at several
+//! depths from fixed midgame positions, and compare the search with and
+//! without alpha-beta pruning and center-first move ordering.
 #![allow(missing_docs, clippy::missing_docs_in_private_items)]
 
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use game::Game;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use game::{Board, Game, Player};
 use players::MinimaxPlayer;
 
 criterion_main!(benches);
-criterion_group!(benches, minimax_player_benchmark);
+criterion_group!(
+	benches,
+	minimax_player_benchmark,
+	minimax_depth_benchmark,
+	minimax_pruning_benchmark,
+	minimax_move_ordering_benchmark
+);
+
+/// Midgame positions as sequences of played columns, starting with X.
+const POSITIONS: [&str; 3] = ["3324421055", "433522661340", "2345332410164"];
+
+/// Search depths to benchmark.
+const DEPTHS: [usize; 3] = [3, 5, 7];
 
 fn minimax_player_benchmark(c: &mut Criterion) {
 	c.bench_function("minimax_player", move |b| {
@@ -18,3 +32,65 @@ fn minimax_player_benchmark(c: &mut Criterion) {
 		});
 	});
 }
+
+/// Build the board by playing the given sequence of columns.
+fn position(moves: &str) -> Board {
+	let mut board = Board::default();
+	for column in moves.chars() {
+		let column = column.to_digit(10).expect("column digit") as usize;
+		board.put_tile(column, board.whos_turn()).expect("legal move");
+	}
+	assert!(board.game_result().is_none(), "position must not be finished");
+	board
+}
+
+fn minimax_depth_benchmark(c: &mut Criterion) {
+	let mut group = c.benchmark_group("minimax_depth");
+	group.sample_size(10);
+	for depth in DEPTHS {
+		let player = MinimaxPlayer::new_1(depth);
+		for moves in POSITIONS {
+			let board = position(moves);
+			group.bench_with_input(
+				BenchmarkId::new(format!("depth_{depth}"), moves),
+				&board,
+				|b, board| {
+					b.iter(|| player.make_move(black_box(board), board.whos_turn()));
+				},
+			);
+		}
+	}
+	group.finish();
+}
+
+fn minimax_pruning_benchmark(c: &mut Criterion) {
+	let mut group = c.benchmark_group("minimax_pruning");
+	group.sample_size(10);
+	let pruned = MinimaxPlayer::new_1(6);
+	let full = MinimaxPlayer::new_1(6).without_pruning();
+	for moves in POSITIONS {
+		let board = position(moves);
+		for (name, player) in [("pruned", &pruned), ("full", &full)] {
+			group.bench_with_input(BenchmarkId::new(name, moves), &board, |b, board| {
+				b.iter(|| player.make_move(black_box(board), board.whos_turn()));
+			});
+		}
+	}
+	group.finish();
+}
+
+fn minimax_move_ordering_benchmark(c: &mut Criterion) {
+	let mut group = c.benchmark_group("minimax_move_ordering");
+	group.sample_size(10);
+	let ordered = MinimaxPlayer::new_1(6);
+	let unordered = MinimaxPlayer::new_1(6).without_move_ordering();
+	for moves in POSITIONS {
+		let board = position(moves);
+		for (name, player) in [("center_first", &ordered), ("left_to_right", &unordered)] {
+			group.bench_with_input(BenchmarkId::new(name, moves), &board, |b, board| {
+				b.iter(|| player.make_move(black_box(board), board.whos_turn()));
+			});
+		}
+	}
+	group.finish();
+}
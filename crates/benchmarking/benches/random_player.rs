@@ -0,0 +1,28 @@
+//! Benchmark the move selection of the random player against selecting from
+//! the set of possible moves.
+#![allow(missing_docs, clippy::missing_docs_in_private_items)]
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use game::{Board, Player, Team};
+use players::RandomPlayer;
+use rand::{seq::IteratorRandom, thread_rng};
+
+criterion_main!(benches);
+criterion_group!(benches, random_player_benchmark);
+
+fn random_player_benchmark(c: &mut Criterion) {
+	let mut board = Board::default();
+	for column in [3, 3, 2, 4, 4, 0] {
+		board.put_tile(column, board.whos_turn()).expect("legal move");
+	}
+
+	c.bench_function("random_player_mask", |b| {
+		b.iter(|| RandomPlayer.make_move(black_box(&board), Team::X));
+	});
+	c.bench_function("random_player_hash_set", |b| {
+		b.iter(|| {
+			let possible_moves = black_box(&board).possible_moves();
+			*possible_moves.iter().choose(&mut thread_rng()).expect("No possible moves")
+		});
+	});
+}
@@ -1,18 +1,26 @@
 //! Connect four CLI game implementation.
 #![allow(clippy::print_stdout, clippy::expect_used)]
 
-use game::{Error, Game, GameResult, Team};
+use game::{Board, Error, Game, GameResult, Player, Team};
 use players::{AiValuePlayer, IoPlayer, NdArrayBackend};
 
+/// Command line flag to enable the hint mode.
+const HINTS_FLAG: &str = "--hints";
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+	let hints = std::env::args().skip(1).any(|arg| arg == HINTS_FLAG);
+
 	let model_path = "./model";
-	let ai = AiValuePlayer::<NdArrayBackend>::init(5).load(model_path).unwrap_or_else(|err| {
+	let (ai, err) = AiValuePlayer::<NdArrayBackend>::load_or_init(model_path, 5);
+	if let Some(err) = err {
 		println!("Failed loading model: {err}");
 		println!("Starting with new model");
-		AiValuePlayer::init(5)
-	});
+	}
 
-	let mut game = Game::builder().player_x(&IoPlayer).player_o(&ai).build();
+	let io = IoPlayer::stdio();
+	let hinted = HintedIoPlayer { advisor: &ai, io: &io };
+	let human: &dyn Player = if hints { &hinted } else { &io };
+	let mut game = Game::builder().player_x(human).player_o(&ai).build();
 	let result = match game.run() {
 		Ok(res) => res,
 		Err(Error::FieldFullAtColumn(team)) => {
@@ -34,3 +42,64 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
 	Ok(())
 }
+
+/// Terminal IO player that shows the advisor's evaluation of the possible
+/// moves before each move.
+#[derive(Debug)]
+struct HintedIoPlayer<'a> {
+	/// Player to evaluate the moves.
+	advisor: &'a dyn Player,
+	/// Terminal IO player making the moves.
+	io: &'a IoPlayer,
+}
+
+impl<'a> Player for HintedIoPlayer<'a> {
+	fn make_move(&self, board: &Board, me: Team) -> usize {
+		if let Some(scores) = self.advisor.scored_moves(board, me) {
+			println!("{}", format_move_hints(&scores));
+		}
+		self.io.make_move(board, me)
+	}
+
+	fn name(&self) -> &str {
+		self.io.name()
+	}
+}
+
+/// Format the scores of the possible moves as hints, as evaluated for the
+/// player to move, marking the best move.
+fn format_move_hints(scores: &[(usize, f64)]) -> String {
+	let best = scores
+		.iter()
+		.max_by(|(_, score_a), (_, score_b)| score_a.total_cmp(score_b))
+		.map(|(column, _)| *column);
+
+	let mut hints = String::from("AI evaluation of your moves (higher is better for you):\n");
+	for (column, score) in scores {
+		let value = if *score == f64::MAX {
+			"win".to_owned()
+		} else if *score == f64::MIN {
+			"loss".to_owned()
+		} else {
+			format!("{score:+.3}")
+		};
+		let marker = if Some(*column) == best { " <- best" } else { "" };
+		hints.push_str(&format!("{column}: {value}{marker}\n"));
+	}
+	hints
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn move_hints() {
+		let scores = [(0, -0.25), (2, 0.5), (3, f64::MAX), (6, f64::MIN)];
+		assert_eq!(
+			format_move_hints(&scores),
+			"AI evaluation of your moves (higher is better for you):\n0: -0.250\n2: +0.500\n3: \
+			 win <- best\n6: loss\n"
+		);
+	}
+}
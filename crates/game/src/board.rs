@@ -1,32 +1,52 @@
 //! Connect four game board implementation.
 
-use std::{collections::HashSet, fmt::Display};
+use std::{collections::HashSet, fmt::Display, str::FromStr};
 
+use rand::{seq::SliceRandom, Rng};
 use yansi::Paint;
 
 use crate::Error;
 
-/// Width of the connect four field. Must fit in a u8.
-const W: usize = 7;
-/// Height of the connect four field. Must fit in a u8.
-const H: usize = 6;
+/// The standard connect four board with 7 columns and 6 rows.
+pub type ConnectFour = Board<7, 6, 4>;
 
-/// Connect four game board instance.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct Board {
-	/// The field to play on. It is a WxH (columns x rows) field organized in a
-	/// 1D array.
+/// Connect four game board instance with `W` columns and `H` rows, where `K`
+/// tiles in a row win. The board must fit into a 128 bit bitboard, i.e.
+/// `W * (H + 1) <= 128`, which allows boards up to e.g. 10x7 or 9x8. Defaults
+/// to the standard 7x6 board with four to win, see [`ConnectFour`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Board<const W: usize = 7, const H: usize = 6, const K: usize = 4> {
+	/// The field to play on as bitboards of the tiles of each team, indexed by
+	/// [`Team::index`].
 	///
-	/// Unlike one might assume, the way to address a field is as follows:
-	/// `field[x][y] = field[x*H + y]` (not `y*W + x`)
-	/// This should allow faster iteration when placing new tiles.
-	///
-	/// The first tile is put to y = 0, the last to y = H - 1.
-	field: [Option<Team>; W * H],
+	/// Position (x, y) is bit `x * (H + 1) + y`, i.e. each column takes `H + 1`
+	/// bits, the first tile is put to y = 0, the last to y = H - 1. The top
+	/// bit of each column stays empty, so that lines of tiles can be found by
+	/// shifting without wrapping around into the next column.
+	masks: [u128; Team::COUNT],
+	/// Zobrist hash of the field, updated incrementally with every change of a
+	/// tile. See [`Board::zobrist_hash`].
+	hash: u64,
+}
+
+/// Seed of the Zobrist keys, see [`zobrist_key`].
+const ZOBRIST_SEED: u64 = 0xC0FF_EE00_5EED_0004;
+
+/// Zobrist key of a tile of the team at the flat field index `x * H + y`. The
+/// keys are the outputs of the splitmix64 generator seeded with
+/// [`ZOBRIST_SEED`], computed on demand, so they are the same in every run and
+/// on every platform and work for any board size.
+fn zobrist_key(index: usize, team: Team) -> u64 {
+	let step = (index * Team::COUNT + team.index()) as u64 + 1;
+	let mut z = ZOBRIST_SEED.wrapping_add(step.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+	z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+	z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+	z ^ (z >> 31)
 }
 
 /// Team identifiers, X and O.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Team {
 	/// Team X.
 	X,
@@ -36,6 +56,7 @@ pub enum Team {
 
 /// Game result.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum GameResult {
 	/// There is a draw.
 	Draw,
@@ -43,24 +64,95 @@ pub enum GameResult {
 	Winner(Team),
 }
 
-impl Default for Board {
+impl Default for ConnectFour {
 	/// Make new empty board.
 	fn default() -> Self {
-		Self { field: [None; W * H] }
+		Self::empty()
 	}
 }
 
-impl Board {
+impl<const W: usize, const H: usize, const K: usize> Board<W, H, K> {
+	/// Compile time check that the board fits into the bitboards.
+	const FITS_BITBOARD: () =
+		assert!(W * (H + 1) <= 128, "Board does not fit into a 128 bit bitboard");
+	/// Compile time check that the board fits into the 64 bits of the keys
+	/// and bitmasks, which is the case for the standard board.
+	const FITS_U64: () = assert!(W * (H + 1) <= 64, "Board does not fit into 64 bits");
+	/// Bitboard with the bottom cell of every column set.
+	const BOTTOM: u128 = {
+		let mut mask = 0;
+		let mut x = 0;
+		while x < W {
+			mask |= 1 << (x * (H + 1));
+			x += 1;
+		}
+		mask
+	};
+	/// Bitboard with every cell of the field set.
+	const FULL: u128 = Self::BOTTOM * ((1 << H) - 1);
+
+	/// Make new empty board of any size. [`Default`] is only implemented for
+	/// the standard [`ConnectFour`] board, so that `Board::default()` needs no
+	/// type annotations.
+	#[must_use]
+	pub const fn empty() -> Self {
+		#[allow(clippy::let_unit_value)] // Evaluates the size check.
+		let () = Self::FITS_BITBOARD;
+		Self { masks: [0; Team::COUNT], hash: 0 }
+	}
+
 	/// Get the dimensions of the board. Returns (Widht, Height).
 	#[must_use]
 	pub fn dimensions(&self) -> (usize, usize) {
 		(W, H)
 	}
 
-	/// Get access to the raw underlying board data.
+	/// Get the tiles of the board as flat list with index `x * H + y`. The
+	/// list is materialized from the bitboards on every call.
 	#[must_use]
-	pub fn field(&self) -> &[Option<Team>] {
-		&self.field
+	pub fn field(&self) -> Vec<Option<Team>> {
+		self.iter_cells().map(|(_, _, tile)| tile).collect()
+	}
+
+	/// Bit of position (`x`, `y`) in the bitboards.
+	const fn bit(x: usize, y: usize) -> u128 {
+		1 << (x * (H + 1) + y)
+	}
+
+	/// Get the tile at position (`x`, `y`), which must be in bounds.
+	fn tile(&self, x: usize, y: usize) -> Option<Team> {
+		let bit = Self::bit(x, y);
+		Team::all().find(|team| self.masks[team.index()] & bit != 0)
+	}
+
+	/// Bitboard of all set tiles.
+	fn occupied(&self) -> u128 {
+		self.masks.iter().fold(0, |occupied, mask| occupied | mask)
+	}
+
+	/// Number of tiles in the column, 0 for out of bounds columns.
+	fn column_height(&self, column: usize) -> usize {
+		if column >= W {
+			return 0;
+		}
+		let column_mask = ((1 << H) - 1) << (column * (H + 1));
+		(self.occupied() & column_mask).count_ones() as usize
+	}
+
+	/// Check whether the bitboard contains `K` tiles in a row in any
+	/// direction, using the shift-and-AND trick: shifting by 1 checks
+	/// vertically, by `H + 1` horizontally, by `H + 2` diagonally upwards and
+	/// by `H` diagonally downwards. The empty top bits of the columns prevent
+	/// false positives across columns.
+	fn connects(mask: u128) -> bool {
+		[1, H + 1, H + 2, H].into_iter().any(|shift| {
+			// Keeps the tiles starting a line of the length of the loop count.
+			let mut line = mask;
+			for _ in 1..K {
+				line &= line >> shift;
+			}
+			line != 0
+		})
 	}
 
 	/// Get current state of the board, returning whether there is a result and
@@ -69,77 +161,84 @@ impl Board {
 	/// without checking the state in between.
 	#[must_use]
 	pub fn game_result(&self) -> Option<GameResult> {
-		// First check in the y direction as it should be the fastest.
-		for x in 0..W {
-			for y in 0..H - 3 {
-				if let Some(team) = self.field[x * H + y] {
-					if self.field[x * H + y + 1] == Some(team)
-						&& self.field[x * H + y + 2] == Some(team)
-						&& self.field[x * H + y + 3] == Some(team)
-					{
-						return Some(GameResult::Winner(team));
-					}
-				}
-			}
+		if let Some(team) = self.winner() {
+			return Some(GameResult::Winner(team));
 		}
 
-		// Next check in x direction.
-		for y in 0..H {
-			for x in 0..W - 3 {
-				if let Some(team) = self.field[x * H + y] {
-					if self.field[(x + 1) * H + y] == Some(team)
-						&& self.field[(x + 2) * H + y] == Some(team)
-						&& self.field[(x + 3) * H + y] == Some(team)
-					{
-						return Some(GameResult::Winner(team));
-					}
-				}
-			}
-		}
+		// Otherwise the game is running or drawn (if it is full).
+		self.is_full().then_some(GameResult::Draw)
+	}
 
-		// Next check diagonally upwards.
-		for x in 0..W - 3 {
-			for y in 0..H - 3 {
-				if let Some(team) = self.field[x * H + y] {
-					if self.field[(x + 1) * H + y + 1] == Some(team)
-						&& self.field[(x + 2) * H + y + 2] == Some(team)
-						&& self.field[(x + 3) * H + y + 3] == Some(team)
-					{
-						return Some(GameResult::Winner(team));
-					}
-				}
-			}
-		}
+	/// Check whether any team connected `K` tiles. Unlike
+	/// [`Board::game_result`], this skips the check for a draw.
+	#[must_use]
+	pub fn has_winner(&self) -> bool {
+		self.winner().is_some()
+	}
 
-		// Finally check diagonally downwards.
-		for x in 3..W {
-			for y in 0..H - 3 {
-				if let Some(team) = self.field[x * H + y] {
-					if self.field[(x - 1) * H + y + 1] == Some(team)
-						&& self.field[(x - 2) * H + y + 2] == Some(team)
-						&& self.field[(x - 3) * H + y + 3] == Some(team)
-					{
-						return Some(GameResult::Winner(team));
-					}
-				}
-			}
-		}
+	/// Get the team that connected `K` tiles, if any. Returns on the first
+	/// found connection.
+	fn winner(&self) -> Option<Team> {
+		Team::all().find(|team| Self::connects(self.masks[team.index()]))
+	}
 
-		// Otherwise the game is running or drawn (if it is full).
-		if self.field.iter().any(Option::is_none) {
-			None
-		} else {
-			Some(GameResult::Draw)
+	/// Get the team that connected `K` tiles and the (x, y) coordinates of
+	/// the `K` connected tiles, if any. Checks vertical, horizontal,
+	/// diagonally upwards and diagonally downwards lines in this order and
+	/// returns the first found connection, starting from its lowest tile
+	/// (leftmost for horizontal lines).
+	#[must_use]
+	pub fn winning_line(&self) -> Option<(Team, [(usize, usize); K])> {
+		// Rule out positions without a winner cheaply on the bitboards.
+		self.winner()?;
+
+		let vertical = (0..W).flat_map(|x| (0..H).map(move |y| (x, y, (0, 1))));
+		let horizontal = (0..H).flat_map(|y| (0..W).map(move |x| (x, y, (1, 0))));
+		let upwards = (0..W).flat_map(|x| (0..H).map(move |y| (x, y, (1, 1))));
+		let downwards = (0..W).flat_map(|x| (0..H).map(move |y| (x, y, (-1, 1))));
+		vertical
+			.chain(horizontal)
+			.chain(upwards)
+			.chain(downwards)
+			.find_map(|(x, y, direction)| self.line_at(x, y, direction))
+	}
+
+	/// Get the line of `K` fields starting at (`x`, `y`) in the direction
+	/// (`dx`, `dy`), if they are all tiles of the same team.
+	#[allow(clippy::cast_possible_wrap)] // The board isn't that wide, there is no wraps.
+	fn line_at(
+		&self,
+		x: usize,
+		y: usize,
+		(dx, dy): (isize, isize),
+	) -> Option<(Team, [(usize, usize); K])> {
+		let team = self.tile_at(x, y)?;
+		let mut line = [(x, y); K];
+		for (i, cell) in line.iter_mut().enumerate() {
+			let i = i as isize;
+			*cell = (x.checked_add_signed(dx * i)?, y.checked_add_signed(dy * i)?);
+			if self.tile_at(cell.0, cell.1) != Some(team) {
+				return None;
+			}
 		}
+		Some((team, line))
 	}
 
-	/// Get safe access to a tile on the field, returning None if the
-	/// coordinates are out of bounds, as if the field is empty.
-	fn field_get_safe(&self, x: usize, y: usize) -> Option<Team> {
+	/// Get the tile at position (`x`, `y`), with `y` counting from the
+	/// bottom. Returns None if the coordinates are out of bounds, as if the
+	/// field is empty.
+	#[must_use]
+	pub fn tile_at(&self, x: usize, y: usize) -> Option<Team> {
 		if x >= W || y >= H {
 			return None;
 		}
-		self.field[x * H + y]
+		self.tile(x, y)
+	}
+
+	/// Iterate over all cells of the board as `(x, y, tile)`, column by column
+	/// from the bottom up, i.e. in the order of [`Board::field`].
+	pub fn iter_cells(&self) -> impl Iterator<Item = (usize, usize, Option<Team>)> + '_ {
+		(0..W).flat_map(move |x| (0..H).map(move |y| (x, y, self.tile(x, y))))
 	}
 
 	/// Get current state of the board, returning whether there is a result and
@@ -147,112 +246,422 @@ impl Board {
 	/// return wrong results if called too late.
 	#[must_use]
 	pub fn game_result_on_change(&self, column: usize) -> Option<GameResult> {
-		let x = column;
-
-		// Get y position of the tile.
-		let mut y = H - 1;
-		for _ in 0..H {
-			if self.field[x * H + y].is_some() {
-				break;
-			} else {
-				y = y.wrapping_sub(1);
-			}
-		}
 		// Get the tile, return game running if the column is all empty.
-		let Some(team) = self.field_get_safe(x, y) else {
-			return None;
-		};
+		let y = self.column_height(column).checked_sub(1)?;
+		let team = self.tile_at(column, y)?;
 
-		// Check if there is a win in x direction.
-		if (self.field_get_safe(x.wrapping_sub(3), y) == Some(team)
-			&& self.field_get_safe(x.wrapping_sub(2), y) == Some(team)
-			&& self.field_get_safe(x.wrapping_sub(1), y) == Some(team))
-			|| (self.field_get_safe(x.wrapping_sub(2), y) == Some(team)
-				&& self.field_get_safe(x.wrapping_sub(1), y) == Some(team)
-				&& self.field_get_safe(x.wrapping_add(1), y) == Some(team))
-			|| (self.field_get_safe(x.wrapping_sub(1), y) == Some(team)
-				&& self.field_get_safe(x.wrapping_add(1), y) == Some(team)
-				&& self.field_get_safe(x.wrapping_add(2), y) == Some(team))
-			|| (self.field_get_safe(x.wrapping_add(1), y) == Some(team)
-				&& self.field_get_safe(x.wrapping_add(2), y) == Some(team)
-				&& self.field_get_safe(x.wrapping_add(3), y) == Some(team))
-		{
+		// Check for a connection of the team.
+		if Self::connects(self.masks[team.index()]) {
 			return Some(GameResult::Winner(team));
 		}
 
-		// Check if there is a win in y direction. There cannot be any tiles on top, we
-		// picked to most top one.
-		if self.field_get_safe(x, y.wrapping_sub(3)) == Some(team)
-			&& self.field_get_safe(x, y.wrapping_sub(2)) == Some(team)
-			&& self.field_get_safe(x, y.wrapping_sub(1)) == Some(team)
-		{
-			return Some(GameResult::Winner(team));
-		}
+		// Otherwise the game is running or drawn (if it is full).
+		self.is_full().then_some(GameResult::Draw)
+	}
 
-		// Check if there is a win in diagonal up direction.
-		if (self.field_get_safe(x.wrapping_sub(3), y.wrapping_sub(3)) == Some(team)
-			&& self.field_get_safe(x.wrapping_sub(2), y.wrapping_sub(2)) == Some(team)
-			&& self.field_get_safe(x.wrapping_sub(1), y.wrapping_sub(1)) == Some(team))
-			|| (self.field_get_safe(x.wrapping_sub(2), y.wrapping_sub(2)) == Some(team)
-				&& self.field_get_safe(x.wrapping_sub(1), y.wrapping_sub(1)) == Some(team)
-				&& self.field_get_safe(x.wrapping_add(1), y.wrapping_add(1)) == Some(team))
-			|| (self.field_get_safe(x.wrapping_sub(1), y.wrapping_sub(1)) == Some(team)
-				&& self.field_get_safe(x.wrapping_add(1), y.wrapping_add(1)) == Some(team)
-				&& self.field_get_safe(x.wrapping_add(2), y.wrapping_add(2)) == Some(team))
-			|| (self.field_get_safe(x.wrapping_add(1), y.wrapping_add(1)) == Some(team)
-				&& self.field_get_safe(x.wrapping_add(2), y.wrapping_add(2)) == Some(team)
-				&& self.field_get_safe(x.wrapping_add(3), y.wrapping_add(3)) == Some(team))
-		{
-			return Some(GameResult::Winner(team));
-		}
+	/// Check that [`Board::game_result`] and [`Board::game_result_on_change`]
+	/// agree, given the column the last tile was put into. This holds as long
+	/// as the game was still running before the last tile, so it is meant as a
+	/// sanity check for tests and debugging.
+	#[must_use]
+	pub fn verify_result_consistency(&self, last_col: usize) -> bool {
+		self.game_result() == self.game_result_on_change(last_col)
+	}
 
-		// Check if there is a win in diagonal down direction.
-		if (self.field_get_safe(x.wrapping_sub(3), y.wrapping_add(3)) == Some(team)
-			&& self.field_get_safe(x.wrapping_sub(2), y.wrapping_add(2)) == Some(team)
-			&& self.field_get_safe(x.wrapping_sub(1), y.wrapping_add(1)) == Some(team))
-			|| (self.field_get_safe(x.wrapping_sub(2), y.wrapping_add(2)) == Some(team)
-				&& self.field_get_safe(x.wrapping_sub(1), y.wrapping_add(1)) == Some(team)
-				&& self.field_get_safe(x.wrapping_add(1), y.wrapping_sub(1)) == Some(team))
-			|| (self.field_get_safe(x.wrapping_sub(1), y.wrapping_add(1)) == Some(team)
-				&& self.field_get_safe(x.wrapping_add(1), y.wrapping_sub(1)) == Some(team)
-				&& self.field_get_safe(x.wrapping_add(2), y.wrapping_sub(2)) == Some(team))
-			|| (self.field_get_safe(x.wrapping_add(1), y.wrapping_sub(1)) == Some(team)
-				&& self.field_get_safe(x.wrapping_add(2), y.wrapping_sub(2)) == Some(team)
-				&& self.field_get_safe(x.wrapping_add(3), y.wrapping_sub(3)) == Some(team))
-		{
-			return Some(GameResult::Winner(team));
+	/// Return all windows of `K` fields in a row (in any direction) that pass
+	/// through the given field. The windows are given as (x, y) coordinates,
+	/// ordered by direction: horizontal, vertical, diagonal upwards, diagonal
+	/// downwards. Returns no windows for out of bounds coordinates.
+	#[must_use]
+	#[allow(clippy::cast_possible_wrap)] // K is at most the board size, there is no wraps.
+	pub fn windows_through(&self, x: usize, y: usize) -> Vec<[(usize, usize); K]> {
+		let mut windows = Vec::new();
+		if x >= W || y >= H {
+			return windows;
 		}
 
-		// Otherwise the game is running or drawn (if it is full).
-		if self.field.iter().any(Option::is_none) {
-			None
-		} else {
-			Some(GameResult::Draw)
+		for (displace_x, displace_y) in [(1, 0), (0, 1), (1, 1), (1, -1_isize)] {
+			for offset in 0..K as isize {
+				let window = (0..K as isize)
+					.map(|i| {
+						let window_x = x.checked_add_signed(displace_x * (i - offset))?;
+						let window_y = y.checked_add_signed(displace_y * (i - offset))?;
+						(window_x < W && window_y < H).then_some((window_x, window_y))
+					})
+					.collect::<Option<Vec<_>>>();
+				if let Some(window) = window.and_then(|window| window.try_into().ok()) {
+					windows.push(window);
+				}
+			}
 		}
+		windows
 	}
 
 	/// Return whos turn it is. Just checks the number of set tiles. Empty field
 	/// means X, next O, etc..
 	#[must_use]
 	pub fn whos_turn(&self) -> Team {
-		if self.field.iter().filter(|t| t.is_some()).count() % 2 == 0 {
-			Team::X
-		} else {
-			Team::O
+		Team::from_turn(self.filled())
+	}
+
+	/// Get the number of tiles on the board in O(1), by counting the set bits
+	/// of the bitboards.
+	#[must_use]
+	pub fn filled(&self) -> usize {
+		self.occupied().count_ones() as usize
+	}
+
+	/// Check whether every field of the board is filled, in O(1).
+	#[must_use]
+	pub fn is_full(&self) -> bool {
+		self.occupied() == Self::FULL
+	}
+
+	/// Create a random reachable position by playing `moves` uniformly random
+	/// legal moves from the empty board, alternating teams starting with X.
+	/// Stops early when the game ends, so the position may be finished.
+	#[must_use]
+	pub fn random_position(moves: usize, rng: &mut impl Rng) -> Self {
+		let mut board = Self::empty();
+		for _ in 0..moves {
+			let legal_moves =
+				(0..W).filter(|column| board.is_legal_move(*column)).collect::<Vec<_>>();
+			let Some(column) = legal_moves.choose(rng) else {
+				break;
+			};
+			if !matches!(board.play(*column, board.whos_turn()), Ok(None)) {
+				break;
+			}
 		}
+		board
+	}
+
+	/// Clear the board in place, so that it can be reused for the next game.
+	pub fn reset(&mut self) {
+		*self = Self::empty();
 	}
 
 	/// Return the set of possible moves, i.e. which columns still have open
 	/// fields.
 	#[must_use]
 	pub fn possible_moves(&self) -> HashSet<usize> {
-		let mut set = HashSet::with_capacity(W);
+		(0..W).filter(|x| self.is_legal_move(*x)).collect()
+	}
+
+	/// Return the possible moves as bitmask, bit `x` being set if column `x`
+	/// still has open fields. Unlike [`Board::possible_moves`], this doesn't
+	/// allocate.
+	#[must_use]
+	pub fn possible_moves_mask(&self) -> u64 {
+		(0..W).filter(|x| self.is_legal_move(*x)).fold(0, |mask, x| mask | 1 << x)
+	}
+
+	/// Get the legal columns ordered from the center outwards, e.g. `3, 4, 2,
+	/// 5, 1, 6, 0` on the empty board. Central moves tend to be stronger, so
+	/// searching them first speeds up alpha-beta pruning.
+	#[must_use]
+	pub fn moves_center_ordered(&self) -> Vec<usize> {
+		let mut moves = (0..W).filter(|column| self.is_legal_move(*column)).collect::<Vec<_>>();
+		// Twice the distance to `W / 2`, which is the center column for even widths
+		// and half a column right of it for odd widths. The stable sort keeps left
+		// columns first on ties.
+		moves.sort_by_key(|column| (2 * column).abs_diff(W));
+		moves
+	}
+
+	/// Get the legal columns ordered from the center outwards like
+	/// [`Board::moves_center_ordered`], but with the left one of equally
+	/// central columns first, e.g. `3, 2, 4, 1, 5, 0, 6` on the empty board.
+	#[must_use]
+	pub fn possible_moves_ordered(&self) -> Vec<usize> {
+		let mut moves = (0..W).filter(|column| self.is_legal_move(*column)).collect::<Vec<_>>();
+		// Twice the distance to the center, the stable sort keeps left columns first on
+		// ties.
+		moves.sort_by_key(|column| (2 * column + 1).abs_diff(W));
+		moves
+	}
+
+	/// Check whether a tile can be put into the given column, i.e. whether the
+	/// column is in bounds and not full yet.
+	#[must_use]
+	pub fn is_legal_move(&self, column: usize) -> bool {
+		column < W && self.occupied() & Self::bit(column, H - 1) == 0
+	}
+
+	/// Check whether putting a tile of the team into the column would connect
+	/// `K`, without changing the board. Only the lines through the landing
+	/// cell are checked. Returns false for full or out of bounds columns.
+	#[must_use]
+	pub fn is_winning_move(&self, column: usize, team: Team) -> bool {
+		if !self.is_legal_move(column) {
+			return false;
+		}
+
+		let bit = Self::bit(column, self.column_height(column));
+		let mask = self.masks[team.index()] | bit;
+		[1, H + 1, H + 2, H].into_iter().any(|shift| {
+			let mut length = 1;
+			let (mut up, mut down) = (bit << shift, bit >> shift);
+			while up & mask != 0 {
+				length += 1;
+				up <<= shift;
+			}
+			while down & mask != 0 {
+				length += 1;
+				down >>= shift;
+			}
+			length >= K
+		})
+	}
+
+	/// Check whether putting a tile of the team into the column creates a fork,
+	/// i.e. the team would then have two or more distinct immediate winning
+	/// moves. Returns false for illegal moves and moves that end the game.
+	#[must_use]
+	pub fn creates_fork(&self, column: usize, team: Team) -> bool {
+		let mut board = *self;
+		if !matches!(board.play(column, team), Ok(None)) {
+			return false;
+		}
+
+		let winning_moves = (0..W)
+			.filter(|column| {
+				let mut next = board;
+				matches!(next.play(*column, team), Ok(Some(GameResult::Winner(winner))) if winner == team)
+			})
+			.count();
+		winning_moves >= 2
+	}
+
+	/// Check whether putting a tile of `me` into the column is a blunder, i.e.
+	/// the opponent then has an immediate winning move. Returns false for
+	/// illegal moves and moves that end the game.
+	#[must_use]
+	pub fn is_blunder(&self, column: usize, me: Team) -> bool {
+		let mut board = *self;
+		if !matches!(board.play(column, me), Ok(None)) {
+			return false;
+		}
+
+		(0..W).any(|column| {
+			let mut next = board;
+			matches!(next.play(column, me.other()), Ok(Some(GameResult::Winner(winner))) if winner != me)
+		})
+	}
+
+	/// Compute how putting a tile of the team into the column changes the
+	/// number of open threes, as `(own_change, opponent_change)`. An open
+	/// three is a window of `K` fields in a row (in any direction) with `K - 1`
+	/// tiles of a team and one empty field, i.e. three for connect four. A
+	/// positive own change means the move sets up new threats, a negative
+	/// opponent change means it blocks threats. Completing a three counts as
+	/// losing it. Returns `(0, 0)` for illegal moves.
+	#[must_use]
+	#[allow(clippy::cast_possible_wrap)] // There are at most 4 * K windows through a field.
+	pub fn threat_delta(&self, column: usize, team: Team) -> (i32, i32) {
+		let mut after = *self;
+		if after.put_tile(column, team).is_err() {
+			return (0, 0);
+		}
+		let Some(y) = (0..H).rev().find(|y| after.tile(column, *y).is_some()) else {
+			return (0, 0);
+		};
+
+		let open_threes = |board: &Self, team: Team| {
+			board
+				.windows_through(column, y)
+				.into_iter()
+				.filter(|window| {
+					let tiles = window.map(|(x, y)| board.tile(x, y));
+					tiles.iter().filter(|tile| **tile == Some(team)).count() == K - 1
+						&& tiles.contains(&None)
+				})
+				.count() as i32
+		};
+		(
+			open_threes(&after, team) - open_threes(self, team),
+			open_threes(&after, team.other()) - open_threes(self, team.other()),
+		)
+	}
+
+	/// Find the best move for `me` by looking one move ahead: every legal move
+	/// is valued by the heuristic applied to the resulting position, except
+	/// that winning moves are always best and drawing moves are valued 0.
+	/// NaN values are treated as worst. Ties go to the leftmost column. Returns
+	/// `None` if there are no legal moves.
+	#[must_use]
+	pub fn greedy_move(&self, me: Team, heuristic: impl Fn(&Self, Team) -> f64) -> Option<usize> {
+		// Iterate in reverse, as `max_by` returns the last of equal elements.
+		(0..W)
+			.rev()
+			.filter_map(|column| {
+				let mut board = *self;
+				let value = match board.play(column, me).ok()? {
+					Some(GameResult::Winner(_)) => f64::INFINITY,
+					Some(GameResult::Draw) => 0.0,
+					None => heuristic(&board, me),
+				};
+				Some((column, if value.is_nan() { f64::NEG_INFINITY } else { value }))
+			})
+			.max_by(|(_, value_a), (_, value_b)| value_a.total_cmp(value_b))
+			.map(|(column, _)| column)
+	}
+
+	/// Get the empty cells that become playable after exactly one more tile in
+	/// their column, i.e. the cells one above the landing cell of each column,
+	/// as (x, y) positions.
+	#[must_use]
+	pub fn next_playable_cells(&self) -> Vec<(usize, usize)> {
+		let mut cells = Vec::new();
+		for x in 0..W {
+			if let Some(landing) = (0..H).find(|y| self.tile(x, *y).is_none()) {
+				if landing + 1 < H {
+					cells.push((x, landing + 1));
+				}
+			}
+		}
+		cells
+	}
+
+	/// Encode the board as a single input plane for the neural networks from
+	/// the view of `me`: 1 for own tiles, -1 for opponent tiles and 0 for
+	/// empty fields. The values are in the order of [`Board::field`], i.e.
+	/// column-major with index `x * H + y` and `y` counting from the bottom.
+	/// The networks reshape this to `[width, height]` (7x6) and transpose it
+	/// to their `[height, width]` (6x7) input layout.
+	#[must_use]
+	pub fn to_input_plane(&self, me: Team) -> Vec<f32> {
+		self.iter_cells()
+			.map(|(_, _, tile)| match tile {
+				None => 0.0,
+				Some(team) if team == me => 1.0,
+				_ => -1.0,
+			})
+			.collect()
+	}
+
+	/// Get a unique key for this position, which is stable across runs and
+	/// platforms. Each column is encoded in `H + 1` bits: one bit per tile,
+	/// set for [`Team::X`], followed by a marker bit above the top tile. Only
+	/// available for boards with `W * (H + 1) <= 64`, others fail to compile.
+	#[must_use]
+	#[allow(clippy::cast_possible_truncation)] // The size is checked at compile time.
+	pub fn key(&self) -> u64 {
+		#[allow(clippy::let_unit_value)] // Evaluates the size check.
+		let () = Self::FITS_U64;
+		// Adding the bottom row carries over the tiles of each column into the
+		// marker bit.
+		(self.masks[Team::X.index()] | (self.occupied() + Self::BOTTOM)) as u64
+	}
+
+	/// Get the occupancy of the board as bitmasks `(x_mask, o_mask)` of the
+	/// cells of each team. The bit order is column-major like in
+	/// [`Board::key`]: position (x, y) is bit `x * (H + 1) + y`, leaving the
+	/// top bit of each column's `H + 1` bits empty, which is the layout common
+	/// bitboard solvers use and the board's internal representation. Only
+	/// available for boards with `W * (H + 1) <= 64`, others fail to compile.
+	#[must_use]
+	#[allow(clippy::cast_possible_truncation)] // The size is checked at compile time.
+	pub fn bitmasks(&self) -> (u64, u64) {
+		#[allow(clippy::let_unit_value)] // Evaluates the size check.
+		let () = Self::FITS_U64;
+		(self.masks[Team::X.index()] as u64, self.masks[Team::O.index()] as u64)
+	}
+
+	/// Count the maximal runs of connected tiles of the team in all four
+	/// directions (horizontal, vertical and both diagonals). Index `i` holds
+	/// the number of runs of length `i + 1`, runs of `K` or more are counted
+	/// at index `K - 1`. Runs are counted per direction, so a single isolated
+	/// tile counts as four runs of length 1.
+	#[must_use]
+	#[allow(clippy::cast_possible_wrap)] // The board isn't that wide, there is no wraps.
+	pub fn run_histogram(&self, team: Team) -> [usize; K] {
+		let is_team = |x: isize, y: isize| {
+			x >= 0 && y >= 0 && self.tile_at(x as usize, y as usize) == Some(team)
+		};
+
+		let mut histogram = [0; K];
+		for x in 0..W as isize {
+			for y in 0..H as isize {
+				if !is_team(x, y) {
+					continue;
+				}
+				for (dx, dy) in [(1, 0), (0, 1), (1, 1), (1, -1)] {
+					// Only count from the start of a run.
+					if is_team(x - dx, y - dy) {
+						continue;
+					}
+					let mut length = 0;
+					while is_team(x + length * dx, y + length * dy) {
+						length += 1;
+					}
+					histogram[(length as usize).min(K) - 1] += 1;
+				}
+			}
+		}
+		histogram
+	}
+
+	/// Get the board mirrored at the central column.
+	#[must_use]
+	pub fn mirrored(&self) -> Self {
+		let mut mirrored = Self::empty();
 		for x in 0..W {
-			if self.field[x * H + H - 1].is_none() {
-				set.insert(x);
+			for y in 0..H {
+				mirrored.set_tile(W - 1 - x, y, self.tile(x, y));
 			}
 		}
-		set
+		mirrored
+	}
+
+	/// Get the key of the canonical orientation of this position, i.e. the
+	/// smaller key of the board and its mirror image. Mirrored positions share
+	/// the same canonical key. Only available for boards with
+	/// `W * (H + 1) <= 64`, like [`Board::key`].
+	#[must_use]
+	pub fn canonical_key(&self) -> u64 {
+		self.key().min(self.mirrored().key())
+	}
+
+	/// Count the cells in which this board and the other board differ, i.e.
+	/// the Hamming distance between the positions.
+	#[must_use]
+	pub fn hamming_distance(&self, other: &Self) -> usize {
+		let differences = self.masks.iter().zip(other.masks).fold(0, |diff, (a, b)| diff | (a ^ b));
+		differences.count_ones() as usize
+	}
+
+	/// Compute the Zobrist hash of the position from scratch: the XOR of the
+	/// keys of all tiles, which are fixed per cell and team. Equal positions
+	/// have equal hashes, no matter the move order. The board keeps this hash
+	/// up to date incrementally, so [`Board::zobrist`] is cheaper to use.
+	#[must_use]
+	pub fn zobrist_hash(&self) -> u64 {
+		self.iter_cells()
+			.filter_map(|(x, y, tile)| tile.map(|team| zobrist_key(x * H + y, team)))
+			.fold(0, |hash, key| hash ^ key)
+	}
+
+	/// Get the Zobrist hash of the position in O(1), which is maintained
+	/// incrementally when putting and popping tiles. Equals
+	/// [`Board::zobrist_hash`].
+	#[must_use]
+	pub fn zobrist(&self) -> u64 {
+		self.hash
+	}
+
+	/// Set the tile at position (`x`, `y`), keeping the hash up to date.
+	fn set_tile(&mut self, x: usize, y: usize, tile: Option<Team>) {
+		let index = x * H + y;
+		let bit = Self::bit(x, y);
+		if let Some(team) = self.tile(x, y) {
+			self.hash ^= zobrist_key(index, team);
+			self.masks[team.index()] &= !bit;
+		}
+		if let Some(team) = tile {
+			self.hash ^= zobrist_key(index, team);
+			self.masks[team.index()] |= bit;
+		}
 	}
 
 	/// Put a tile of the specified team to the corresponding column.
@@ -261,21 +670,39 @@ impl Board {
 			return Err(Error::IndexOutOfBounds);
 		}
 
-		for y in 0..H {
-			if self.field[column * H + y].is_none() {
-				self.field[column * H + y] = Some(team);
-				return Ok(());
-			}
+		let y = self.column_height(column);
+		if y >= H {
+			return Err(Error::FieldFullAtColumn(team));
 		}
+		self.set_tile(column, y, Some(team));
+		Ok(())
+	}
 
-		Err(Error::FieldFullAtColumn(team))
+	/// Remove the topmost tile from the column, undoing the last
+	/// [`Board::put_tile`] into it. Returns the team the tile belonged to.
+	pub fn pop_tile(&mut self, column: usize) -> Result<Team, Error> {
+		if column >= W {
+			return Err(Error::IndexOutOfBounds);
+		}
+
+		let y = self.column_height(column).checked_sub(1).ok_or(Error::EmptyColumn)?;
+		let team = self.tile(column, y).ok_or(Error::EmptyColumn)?;
+		self.set_tile(column, y, None);
+		Ok(team)
+	}
+
+	/// Play a move: put a tile of the specified team to the corresponding
+	/// column and return the game result after the move, checked
+	/// incrementally based on the new tile.
+	pub fn play(&mut self, column: usize, team: Team) -> Result<Option<GameResult>, Error> {
+		self.put_tile(column, team)?;
+		Ok(self.game_result_on_change(column))
 	}
 
 	/// Heuristic function to evaluate the board's position. Returns 0.0 for an
 	/// estimated draw, above that for estimated wins and below for estimated
 	/// losses.
 	#[must_use]
-	#[allow(clippy::cast_possible_wrap)] // The board isn't that wide, there is no wraps.
 	pub fn heuristic_1(&self, me: Team) -> f64 {
 		match self.game_result() {
 			Some(GameResult::Draw) => return 0.0,
@@ -286,55 +713,181 @@ impl Board {
 		let mut value = 0.0;
 		for x in 0..W {
 			for y in 0..H {
-				if let Some(team) = self.field[x * H + y] {
-					let mut surrounding = 0.0;
-					for (displace_x, displace_y) in [
-						(1, 0),
-						(1, 1),
-						(0, 1),
-						(-1, 1),
-						(-1, 0),
-						(-1_i32, -1_i32),
-						(0, -1),
-						(1, -1),
-					] {
-						if let Some(field) = self.field.get(
-							(x as i32 + displace_x)
-								.saturating_mul(H as i32)
-								.saturating_add(y as i32 + displace_y) as usize,
-						) {
-							match field {
-								None => surrounding += 0.333,
-								Some(t) if *t == team => surrounding += 1.0,
-								_ => surrounding -= 1.0,
-							}
-						}
-					}
-					if team == me {
-						value += surrounding;
-					} else {
-						value -= surrounding;
+				value += self.heuristic_1_contribution(x, y, me);
+			}
+		}
+		value
+	}
+
+	/// Heuristic function like [`Board::heuristic_1`], additionally valuing
+	/// central tiles and open threes. Every tile gets a bonus by its column,
+	/// peaking at the central column and decreasing outwards, as central tiles
+	/// take part in more lines. Open threes, i.e. windows of `K` with `K - 1`
+	/// tiles of a team and one empty field, are weighted highly. Returns 0.0
+	/// for a draw, `f64::MAX` for a win and `f64::MIN` for a loss, positive
+	/// values are good for `me`.
+	#[must_use]
+	pub fn heuristic_2(&self, me: Team) -> f64 {
+		/// Bonus per tile for each column closer to the center than the edge.
+		const CENTER_WEIGHT: f64 = 0.5;
+		/// Value of an open three.
+		const THREAT_WEIGHT: f64 = 5.0;
+
+		let value = self.heuristic_1(me);
+		if self.game_result().is_some() {
+			return value;
+		}
+
+		let center = W / 2;
+		let center_value = self
+			.iter_cells()
+			.filter_map(|(x, _, tile)| {
+				let bonus = (center - center.abs_diff(x)) as f64 * CENTER_WEIGHT;
+				tile.map(|team| if team == me { bonus } else { -bonus })
+			})
+			.sum::<f64>();
+		let threat_value =
+			(self.open_threes(me) as f64 - self.open_threes(me.other()) as f64) * THREAT_WEIGHT;
+		value + center_value + threat_value
+	}
+
+	/// Count the open threes of the team, i.e. windows of `K` fields in a row
+	/// (in any direction) with `K - 1` tiles of the team and one empty field.
+	#[allow(clippy::cast_possible_wrap)] // K is at most the board size, there is no wraps.
+	fn open_threes(&self, team: Team) -> usize {
+		let mut count = 0;
+		for x in 0..W {
+			for y in 0..H {
+				for (dx, dy) in [(1, 0), (0, 1), (1, 1), (1, -1_isize)] {
+					let window = (0..K as isize)
+						.map(|i| {
+							let x = x.checked_add_signed(dx * i)?;
+							let y = y.checked_add_signed(dy * i)?;
+							(x < W && y < H).then(|| self.tile(x, y))
+						})
+						.collect::<Option<Vec<_>>>();
+					let Some(window) = window else {
+						continue;
+					};
+					if window.iter().filter(|tile| **tile == Some(team)).count() == K - 1
+						&& window.contains(&None)
+					{
+						count += 1;
 					}
 				}
 			}
 		}
+		count
+	}
 
-		value
+	/// Incrementally update the value of [`Board::heuristic_1`] after a tile
+	/// was put into `column`. `previous` is the heuristic value of the board
+	/// before the move, which must not have been finished yet. Only the
+	/// contributions of the new tile and its neighbors are recomputed.
+	#[must_use]
+	pub fn heuristic_1_update(&self, previous: f64, column: usize, me: Team) -> f64 {
+		match self.game_result_on_change(column) {
+			Some(GameResult::Draw) => return 0.0,
+			Some(GameResult::Winner(team)) => return if team == me { f64::MAX } else { f64::MIN },
+			None => {}
+		}
+
+		let Some(y) = (0..H).rev().find(|y| self.tile(column, *y).is_some()) else {
+			return previous;
+		};
+		let neighbors = Self::heuristic_1_neighbors(column, y).collect::<Vec<_>>();
+
+		let mut before = *self;
+		before.set_tile(column, y, None);
+		let mut value = previous;
+		for &neighbor in &neighbors {
+			let (x, y) = (neighbor / H, neighbor % H);
+			value -= before.heuristic_1_contribution(x, y, me);
+			value += self.heuristic_1_contribution(x, y, me);
+		}
+		value + self.heuristic_1_contribution(column, y, me)
+	}
+
+	/// Contribution of the tile at position (`x`, `y`) to
+	/// [`Board::heuristic_1`]. Empty fields don't contribute. A tile's
+	/// contribution only depends on the tile itself and its neighbors.
+	#[must_use]
+	pub fn heuristic_1_contribution(&self, x: usize, y: usize, me: Team) -> f64 {
+		let Some(team) = self.tile_at(x, y) else {
+			return 0.0;
+		};
+
+		let mut surrounding = 0.0;
+		for neighbor in Self::heuristic_1_neighbors(x, y) {
+			match self.tile(neighbor / H, neighbor % H) {
+				None => surrounding += 0.333,
+				Some(t) if t == team => surrounding += 1.0,
+				_ => surrounding -= 1.0,
+			}
+		}
+		if team == me {
+			surrounding
+		} else {
+			-surrounding
+		}
+	}
+
+	/// Field indices considered neighbors of (`x`, `y`) by
+	/// [`Board::heuristic_1`]. The displacement is applied to the flat field
+	/// index, so that neighbors wrap around into adjacent columns at the top
+	/// and bottom. This relation is symmetric.
+	#[allow(clippy::cast_possible_wrap)] // The board isn't that wide, there is no wraps.
+	fn heuristic_1_neighbors(x: usize, y: usize) -> impl Iterator<Item = usize> {
+		[(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1_i32, -1_i32), (0, -1), (1, -1)]
+			.into_iter()
+			.map(move |(displace_x, displace_y)| {
+				(x as i32 + displace_x)
+					.saturating_mul(H as i32)
+					.saturating_add(y as i32 + displace_y) as usize
+			})
+			.filter(|index| *index < W * H)
 	}
 }
 
 impl Team {
-	/// Get the other team.
+	/// Number of teams.
+	pub const COUNT: usize = 2;
+
+	/// All teams in turn order, starting with the team making the first move.
+	pub fn all() -> impl Iterator<Item = Self> {
+		[Self::X, Self::O].into_iter()
+	}
+
+	/// Position of the team in the turn order.
 	#[must_use]
-	pub fn other(&self) -> Self {
+	pub fn index(self) -> usize {
 		match self {
-			Self::X => Self::O,
-			Self::O => Self::X,
+			Self::X => 0,
+			Self::O => 1,
 		}
 	}
+
+	/// Get the team that has its turn after `turns` moves were played.
+	#[must_use]
+	pub fn from_turn(turns: usize) -> Self {
+		Self::all().nth(turns % Self::COUNT).unwrap_or(Self::X)
+	}
+
+	/// Get the team moving after this team.
+	#[must_use]
+	pub fn next(self) -> Self {
+		Self::from_turn(self.index() + 1)
+	}
+
+	/// Get the other team. In the standard two-team game, this is the next
+	/// team.
+	#[must_use]
+	pub fn other(&self) -> Self {
+		self.next()
+	}
 }
 
-impl Display for Board {
+impl<const W: usize, const H: usize, const K: usize> Display for Board<W, H, K> {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		let mut field = String::new();
 		field.push_str(&"----".repeat(W));
@@ -344,7 +897,7 @@ impl Display for Board {
 		field.push('\n');
 		for y in (0..H).rev() {
 			for x in 0..W {
-				field.push(match self.field[x * H + y] {
+				field.push(match self.tile(x, y) {
 					Some(Team::X) => 'X',
 					Some(Team::O) => 'O',
 					None => ' ',
@@ -366,7 +919,7 @@ impl Display for Board {
 	}
 }
 
-impl Board {
+impl<const W: usize, const H: usize, const K: usize> Board<W, H, K> {
 	/// Return a colored string representation of the board.
 	#[must_use]
 	pub fn colored_string(&self, for_team: Team) -> String {
@@ -382,6 +935,50 @@ impl Board {
 	}
 }
 
+impl<const W: usize, const H: usize, const K: usize> Board<W, H, K> {
+	/// Return a string representation of the board for debugging, with column
+	/// indices on top, row indices on the left side and `.` for empty fields.
+	#[must_use]
+	pub fn debug_string(&self) -> String {
+		let mut field = String::from(" ");
+		for x in 0..W {
+			field.push_str(&format!(" {x}"));
+		}
+		for y in (0..H).rev() {
+			field.push_str(&format!("\n{y}"));
+			for x in 0..W {
+				field.push(' ');
+				field.push(match self.tile(x, y) {
+					Some(Team::X) => 'X',
+					Some(Team::O) => 'O',
+					None => '.',
+				});
+			}
+		}
+		field
+	}
+}
+
+impl ConnectFour {
+	/// Reconstruct a position from the compact move list notation, where each
+	/// digit is the 1-indexed column of the next move, e.g. `"4453"`. The
+	/// moves alternate, starting with [`Team::X`]. Trailing whitespace is
+	/// ignored. Characters that are no valid column give
+	/// [`Error::IndexOutOfBounds`], moves into full columns give
+	/// [`Error::FieldFullAtColumn`].
+	pub fn from_moves(moves: &str) -> Result<Self, Error> {
+		let mut board = Self::default();
+		for (turn, c) in moves.trim_end().chars().enumerate() {
+			let column = c
+				.to_digit(10)
+				.and_then(|digit| digit.checked_sub(1))
+				.ok_or(Error::IndexOutOfBounds)?;
+			board.put_tile(column as usize, Team::from_turn(turn))?;
+		}
+		Ok(board)
+	}
+}
+
 impl Display for Team {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		match self {
@@ -391,17 +988,142 @@ impl Display for Team {
 	}
 }
 
+impl FromStr for Team {
+	type Err = Error;
+
+	/// Parse the team from its [`Display`] representation.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"X" => Ok(Team::X),
+			"O" => Ok(Team::O),
+			_ => Err(Error::InvalidNotation(s.to_owned())),
+		}
+	}
+}
+
+impl Display for GameResult {
+	/// Write `Draw` for a draw and the winning team otherwise.
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			GameResult::Draw => f.write_str("Draw"),
+			GameResult::Winner(team) => team.fmt(f),
+		}
+	}
+}
+
+impl FromStr for GameResult {
+	type Err = Error;
+
+	/// Parse the game result from its [`Display`] representation.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"Draw" => Ok(GameResult::Draw),
+			_ => s.parse().map(GameResult::Winner),
+		}
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<const W: usize, const H: usize, const K: usize> serde::Serialize for Board<W, H, K> {
+	/// Serialize the board as flat list of the tiles, in the order of
+	/// [`Board::field`].
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.collect_seq(self.field())
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<'de, const W: usize, const H: usize, const K: usize> serde::Deserialize<'de>
+	for Board<W, H, K>
+{
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let field = Vec::<Option<Team>>::deserialize(deserializer)?;
+		if field.len() != W * H {
+			return Err(serde::de::Error::invalid_length(field.len(), &"a tile for every field"));
+		}
+		let mut board = Self::empty();
+		for (index, tile) in field.into_iter().enumerate() {
+			let (x, y) = (index / H, index % H);
+			if tile.is_some() && y > 0 && board.tile(x, y - 1).is_none() {
+				return Err(serde::de::Error::custom("tile floating above an empty field"));
+			}
+			board.set_tile(x, y, tile);
+		}
+		Ok(board)
+	}
+}
+
 #[cfg(test)]
 mod tests {
-	#![allow(clippy::unwrap_used, clippy::print_stdout)]
+	#![allow(clippy::unwrap_used, clippy::print_stdout, clippy::float_cmp)]
+
+	use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
 
 	use super::*;
 
-	/// Make sure each tile on the board only takes a single byte.
+	/// Width of the standard board.
+	const W: usize = 7;
+	/// Height of the standard board.
+	const H: usize = 6;
+
+	/// Make sure the board only consists of the two bitboards and the cached
+	/// hash, independent of its size.
 	#[test]
 	fn size_of() {
-		let size_of = std::mem::size_of::<Board>();
-		assert_eq!(size_of, W * H);
+		let expected = std::mem::size_of::<([u128; 2], u64)>();
+		assert_eq!(std::mem::size_of::<Board>(), expected);
+		assert_eq!(std::mem::size_of::<Board<8, 7>>(), expected);
+		assert_eq!(std::mem::size_of::<Board<10, 7>>(), expected);
+	}
+
+	#[test]
+	fn ten_by_seven() {
+		let mut board = Board::<10, 7>::empty();
+		assert_eq!(board.dimensions(), (10, 7));
+		assert_eq!(board.possible_moves().len(), 10);
+
+		// Stack the rightmost column, which uses bits beyond 64.
+		for _ in 0..7 {
+			assert_eq!(board.play(9, board.whos_turn()).unwrap(), None);
+		}
+		assert!(!board.is_legal_move(9));
+		assert!(matches!(board.put_tile(9, Team::X), Err(Error::FieldFullAtColumn(Team::X))));
+
+		// Connect four on the bottom row up to the filled column.
+		for column in [6, 6, 7, 7, 8, 8] {
+			assert_eq!(board.play(column, board.whos_turn()).unwrap(), None);
+		}
+		assert_eq!(board.play(5, Team::O).unwrap(), Some(GameResult::Winner(Team::O)));
+		assert_eq!(board.winning_line().unwrap().1, [(5, 0), (6, 0), (7, 0), (8, 0)]);
+		assert_eq!(board.zobrist(), board.zobrist_hash());
+		assert_eq!(board.mirrored().mirrored(), board);
+
+		check_random_games::<10, 7, 4>(10);
+	}
+
+	#[test]
+	fn custom_dimensions() {
+		let mut board = Board::<8, 7>::empty();
+		assert_eq!(board.dimensions(), (8, 7));
+		assert_eq!(board.field().len(), 8 * 7);
+		assert_eq!(board.possible_moves().len(), 8);
+
+		for _ in 0..6 {
+			board.put_tile(7, Team::O).unwrap();
+		}
+		assert_eq!(board.game_result_on_change(7), Some(GameResult::Winner(Team::O)));
+		assert!(board.is_legal_move(7));
+		board.put_tile(7, Team::X).unwrap();
+		assert!(!board.is_legal_move(7));
+		assert!(matches!(board.put_tile(8, Team::X), Err(Error::IndexOutOfBounds)));
+
+		let mut board = Board::<8, 7>::empty();
+		for column in 4..8 {
+			board.put_tile(column, Team::X).unwrap();
+		}
+		assert_eq!(board.game_result(), Some(GameResult::Winner(Team::X)));
+		assert_eq!(board.game_result_on_change(7), Some(GameResult::Winner(Team::X)));
+		assert_eq!(board.mirrored().field()[0], Some(Team::X));
 	}
 
 	#[test]
@@ -605,6 +1327,55 @@ mod tests {
 		assert_eq!(board.game_result_on_change(6), Some(GameResult::Draw));
 	}
 
+	#[test]
+	fn legal_moves() {
+		let mut board = Board::default();
+		for _ in 0..H {
+			board.put_tile(2, Team::X).unwrap();
+		}
+		board.put_tile(4, Team::O).unwrap();
+
+		assert!(!board.is_legal_move(2));
+		assert!(!board.is_legal_move(W));
+		assert!(!board.is_legal_move(usize::MAX));
+		for column in [0, 1, 3, 4, 5, 6] {
+			assert!(board.is_legal_move(column));
+		}
+		assert_eq!(board.possible_moves_mask(), 0b111_1011);
+	}
+
+	#[test]
+	fn windows_through_field() {
+		let board = Board::default();
+		let center = board.windows_through(3, 2);
+		let corner = board.windows_through(0, 0);
+		assert!(center.len() > corner.len());
+		assert_eq!(corner.len(), 3);
+		assert_eq!(center.len(), 13);
+		for window in &center {
+			assert!(window.contains(&(3, 2)));
+		}
+		for window in &corner {
+			assert!(window.contains(&(0, 0)));
+		}
+		assert!(board.windows_through(W, 0).is_empty());
+	}
+
+	#[test]
+	fn debug_string() {
+		let mut board = Board::default();
+		board.put_tile(0, Team::X).unwrap();
+		board.put_tile(0, Team::O).unwrap();
+		board.put_tile(6, Team::X).unwrap();
+
+		let debug = board.debug_string();
+		println!("{debug}");
+		assert!(debug.contains("0 1 2 3 4 5 6"));
+		assert_eq!(debug.lines().count(), H + 1);
+		assert_eq!(debug.lines().last(), Some("0 X . . . . . X"));
+		assert_eq!(debug.lines().nth(H - 1), Some("1 O . . . . . ."));
+	}
+
 	#[test]
 	fn check_state_example_1() {
 		let mut board = Board::default();
@@ -658,4 +1429,649 @@ mod tests {
 		assert_eq!(board.game_result_on_change(5), None);
 		assert_eq!(board.game_result_on_change(6), None);
 	}
+
+	#[test]
+	fn incremental_heuristic_matches_full_recompute() {
+		let mut board = Board::default();
+		let mut value_x = board.heuristic_1(Team::X);
+		let mut value_o = board.heuristic_1(Team::O);
+		let mut team = Team::X;
+		for column in [3, 3, 2, 4, 0, 6, 5, 1, 1, 0, 0, 6, 6, 2, 4, 5] {
+			board.put_tile(column, team).unwrap();
+			assert_eq!(board.game_result(), None);
+			value_x = board.heuristic_1_update(value_x, column, Team::X);
+			value_o = board.heuristic_1_update(value_o, column, Team::O);
+			assert!((value_x - board.heuristic_1(Team::X)).abs() < 1e-9);
+			assert!((value_o - board.heuristic_1(Team::O)).abs() < 1e-9);
+			team = team.other();
+		}
+	}
+
+	#[test]
+	fn canonical_key_is_shared_by_mirror_images() {
+		let mut board = Board::default();
+		board.put_tile(0, Team::X).unwrap();
+		board.put_tile(2, Team::O).unwrap();
+		board.put_tile(0, Team::X).unwrap();
+		let mirrored = board.mirrored();
+
+		assert_eq!(mirrored.field()[6 * H + 1], Some(Team::X));
+		assert_eq!(mirrored.field()[4 * H], Some(Team::O));
+		assert_ne!(board.key(), mirrored.key());
+		assert_eq!(board.canonical_key(), mirrored.canonical_key());
+		assert_eq!(mirrored.mirrored(), board);
+
+		let mut other = board;
+		other.put_tile(0, Team::O).unwrap();
+		assert_ne!(board.key(), other.key());
+		assert_eq!(Board::default().key(), Board::default().canonical_key());
+	}
+
+	#[test]
+	fn next_playable_cells() {
+		let mut board = Board::default();
+		board.put_tile(1, Team::X).unwrap();
+		board.put_tile(1, Team::O).unwrap();
+		board.put_tile(3, Team::X).unwrap();
+		for _ in 0..H - 1 {
+			board.put_tile(5, Team::O).unwrap();
+		}
+		for _ in 0..H {
+			board.put_tile(6, Team::X).unwrap();
+		}
+
+		assert_eq!(board.next_playable_cells(), [(0, 1), (1, 3), (2, 1), (3, 2), (4, 1)]);
+	}
+
+	#[test]
+	fn play_matches_put_tile() {
+		let mut played = Board::default();
+		let mut separate = Board::default();
+		let mut team = Team::X;
+		for column in [3, 3, 4, 4, 5, 5, 6] {
+			let result = played.play(column, team).unwrap();
+			separate.put_tile(column, team).unwrap();
+			assert_eq!(result, separate.game_result_on_change(column));
+			assert_eq!(played, separate);
+			team = team.other();
+		}
+		assert_eq!(played.game_result_on_change(6), Some(GameResult::Winner(Team::X)));
+
+		let mut board = Board::default();
+		for _ in 0..H {
+			board.play(0, Team::O).unwrap();
+		}
+		assert!(matches!(board.play(0, Team::X), Err(Error::FieldFullAtColumn(Team::X))));
+		assert!(matches!(board.put_tile(0, Team::X), Err(Error::FieldFullAtColumn(Team::X))));
+		assert!(matches!(board.play(W, Team::X), Err(Error::IndexOutOfBounds)));
+		assert!(matches!(board.put_tile(W, Team::X), Err(Error::IndexOutOfBounds)));
+	}
+
+	#[test]
+	fn bitmasks() {
+		let mut board = Board::default();
+		for column in [3, 3, 2, 4, 0, 6, 6, 6, 5] {
+			board.put_tile(column, board.whos_turn()).unwrap();
+		}
+		let count = |team| board.field().iter().filter(|tile| **tile == Some(team)).count();
+
+		let (x_mask, o_mask) = board.bitmasks();
+		assert_eq!(x_mask.count_ones() as usize, count(Team::X));
+		assert_eq!(o_mask.count_ones() as usize, count(Team::O));
+		assert_eq!(x_mask & o_mask, 0);
+		assert_eq!(x_mask & (1 << (3 * (H + 1))), 1 << (3 * (H + 1)));
+		assert_eq!(o_mask & (1 << (3 * (H + 1) + 1)), 1 << (3 * (H + 1) + 1));
+
+		// Boards using all 64 bits have the masks and keys too.
+		let mut board = Board::<8, 7>::empty();
+		board.put_tile(7, Team::O).unwrap();
+		assert_eq!(board.bitmasks(), (0, 1 << (7 * 8)));
+		assert_eq!(board.key(), Board::<8, 7>::empty().key() + (1 << (7 * 8)));
+	}
+
+	#[test]
+	fn two_teams() {
+		assert_eq!(Team::all().collect::<Vec<_>>(), [Team::X, Team::O]);
+		assert_eq!(Team::all().count(), Team::COUNT);
+		assert_eq!(Team::X.other(), Team::O);
+		assert_eq!(Team::O.other(), Team::X);
+		assert_eq!(Team::X.next(), Team::O);
+		assert_eq!(Team::O.next(), Team::X);
+		for team in Team::all() {
+			assert_eq!(team.other().other(), team);
+			assert_eq!(Team::from_turn(team.index()), team);
+		}
+
+		let mut board = Board::default();
+		assert_eq!(board.whos_turn(), Team::X);
+		board.put_tile(0, Team::X).unwrap();
+		assert_eq!(board.whos_turn(), Team::O);
+		board.put_tile(0, Team::O).unwrap();
+		assert_eq!(board.whos_turn(), Team::X);
+	}
+
+	#[test]
+	fn has_winner_agrees_with_game_result() {
+		let mut rng = StdRng::seed_from_u64(12345);
+		for _ in 0..200 {
+			let mut board = Board::default();
+			loop {
+				assert_eq!(
+					board.has_winner(),
+					matches!(board.game_result(), Some(GameResult::Winner(_)))
+				);
+				if board.game_result().is_some() {
+					break;
+				}
+				let moves =
+					(0..W).filter(|column| board.is_legal_move(*column)).collect::<Vec<_>>();
+				board.put_tile(*moves.choose(&mut rng).unwrap(), board.whos_turn()).unwrap();
+			}
+		}
+	}
+
+	#[test]
+	fn run_histogram() {
+		let mut board = Board::default();
+		for column in [0, 1, 2, 6, 6, 6, 2] {
+			board.put_tile(column, Team::X).unwrap();
+		}
+		for column in [3, 0, 1, 5, 4] {
+			board.put_tile(column, Team::O).unwrap();
+		}
+		board.put_tile(4, Team::X).unwrap();
+
+		// Threes: bottom row and column 6. Twos: column 2 and the diagonal
+		// from (1, 0) to (2, 1).
+		assert_eq!(board.run_histogram(Team::X), [22, 2, 2, 0]);
+		assert_eq!(board.run_histogram(Team::O), [15, 1, 1, 0]);
+
+		let mut board = Board::default();
+		for _ in 0..5 {
+			board.put_tile(0, Team::X).unwrap();
+		}
+		assert_eq!(board.run_histogram(Team::X), [15, 0, 0, 1]);
+		assert_eq!(board.run_histogram(Team::O), [0; 4]);
+	}
+
+	#[test]
+	fn creates_fork() {
+		let mut board = Board::default();
+		board.put_tile(2, Team::X).unwrap();
+		board.put_tile(2, Team::O).unwrap();
+		board.put_tile(4, Team::X).unwrap();
+		board.put_tile(4, Team::O).unwrap();
+
+		// Only column 3 creates open threats at both 1 and 5.
+		for column in 0..W {
+			assert_eq!(board.creates_fork(column, Team::X), column == 3, "column {column}");
+		}
+		assert!(!board.creates_fork(W, Team::X));
+	}
+
+	#[test]
+	fn notation_round_trip() {
+		for team in Team::all() {
+			assert_eq!(team.to_string().parse::<Team>().unwrap(), team);
+		}
+		for result in [GameResult::Draw, GameResult::Winner(Team::X), GameResult::Winner(Team::O)] {
+			assert_eq!(GameResult::from_str(&result.to_string()).unwrap(), result);
+		}
+		assert_eq!(GameResult::Winner(Team::O).to_string(), "O");
+		assert!("draw".parse::<GameResult>().is_err());
+		assert!("".parse::<Team>().is_err());
+	}
+
+	#[test]
+	fn possible_moves_ordered() {
+		let mut board = Board::default();
+		assert_eq!(board.possible_moves_ordered(), vec![3, 2, 4, 1, 5, 0, 6]);
+		for _ in 0..H {
+			board.put_tile(2, Team::X).unwrap();
+		}
+		assert_eq!(board.possible_moves_ordered(), vec![3, 4, 1, 5, 0, 6]);
+
+		let board = Board::<8, 7>::empty();
+		assert_eq!(board.possible_moves_ordered(), vec![3, 4, 2, 5, 1, 6, 0, 7]);
+	}
+
+	#[test]
+	fn moves_center_ordered() {
+		let mut board = Board::default();
+		assert_eq!(board.moves_center_ordered(), vec![3, 4, 2, 5, 1, 6, 0]);
+
+		for _ in 0..H {
+			board.put_tile(4, Team::X).unwrap();
+			board.put_tile(0, Team::O).unwrap();
+		}
+		assert_eq!(board.moves_center_ordered(), vec![3, 2, 5, 1, 6]);
+
+		// Even widths still include every column.
+		let mut board = Board::<8, 7>::empty();
+		assert_eq!(board.moves_center_ordered(), vec![4, 3, 5, 2, 6, 1, 7, 0]);
+		for _ in 0..7 {
+			board.put_tile(4, Team::X).unwrap();
+		}
+		assert_eq!(board.moves_center_ordered(), vec![3, 5, 2, 6, 1, 7, 0]);
+		assert_eq!(Board::<10, 7>::empty().moves_center_ordered().len(), 10);
+	}
+
+	#[test]
+	fn result_checkers_agree_in_random_games() {
+		let mut rng = StdRng::seed_from_u64(54321);
+		for _ in 0..500 {
+			let mut board = Board::default();
+			loop {
+				let moves =
+					(0..W).filter(|column| board.is_legal_move(*column)).collect::<Vec<_>>();
+				let column = *moves.choose(&mut rng).unwrap();
+				board.put_tile(column, board.whos_turn()).unwrap();
+				assert!(board.verify_result_consistency(column), "{}", board.debug_string());
+				if board.game_result().is_some() {
+					break;
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn greedy_move() {
+		let mut board = Board::default();
+		for column in [2, 2, 3, 3, 4, 4] {
+			board.put_tile(column, board.whos_turn()).unwrap();
+		}
+
+		// Column 1 and 5 win, column 1 comes first.
+		assert_eq!(board.greedy_move(Team::X, |_, _| 0.0), Some(1));
+		assert_eq!(board.greedy_move(Team::X, |_, _| f64::NAN), Some(1));
+		assert_eq!(board.greedy_move(Team::X, |_, _| f64::MAX), Some(1));
+		// Without a win, the heuristic decides.
+		let prefer_6 =
+			|board: &Board, me: Team| if board.field()[6 * H] == Some(me) { 1.0 } else { 0.0 };
+		assert_eq!(board.greedy_move(Team::O, prefer_6), Some(6));
+		assert_eq!(board.greedy_move(Team::O, |_, _| 0.0), Some(0));
+
+		let mut full = Board::default();
+		for column in 0..W {
+			for _ in 0..H {
+				full.put_tile(column, Team::X).unwrap();
+			}
+		}
+		assert_eq!(full.greedy_move(Team::O, |_, _| 0.0), None);
+	}
+
+	#[test]
+	fn reset() {
+		let mut board = Board::default();
+		for column in [3, 3, 2, 6, 0, 1, 1, 5] {
+			board.put_tile(column, board.whos_turn()).unwrap();
+		}
+		board.reset();
+		assert_eq!(board, Board::default());
+		assert_eq!(board.whos_turn(), Team::X);
+	}
+
+	#[cfg(feature = "serde")]
+	#[test]
+	fn serde_round_trip() {
+		let mut board = Board::default();
+		for column in [3, 3, 2, 4, 2, 2, 6, 0] {
+			board.put_tile(column, board.whos_turn()).unwrap();
+		}
+
+		let json = serde_json::to_string(&board).unwrap();
+		let tiles: Vec<Option<Team>> = serde_json::from_str(&json).unwrap();
+		assert_eq!(tiles.len(), W * H);
+		assert_eq!(serde_json::from_str::<Board>(&json).unwrap(), board);
+		assert!(serde_json::from_str::<Board>("[null]").is_err());
+
+		let result = GameResult::Winner(Team::O);
+		let json = serde_json::to_string(&result).unwrap();
+		assert_eq!(serde_json::from_str::<GameResult>(&json).unwrap(), result);
+	}
+
+	#[test]
+	fn from_moves() {
+		let board = Board::from_moves("4453\n ").unwrap();
+		let mut expected = Board::default();
+		for (team, column) in [(Team::X, 3), (Team::O, 3), (Team::X, 4), (Team::O, 2)] {
+			expected.put_tile(column, team).unwrap();
+		}
+		assert_eq!(board, expected);
+		assert_eq!(Board::from_moves("").unwrap(), Board::default());
+
+		assert!(matches!(Board::from_moves("40"), Err(Error::IndexOutOfBounds)));
+		assert!(matches!(Board::from_moves("4 4"), Err(Error::IndexOutOfBounds)));
+		assert!(matches!(Board::from_moves("48"), Err(Error::IndexOutOfBounds)));
+		assert!(matches!(Board::from_moves("1111111"), Err(Error::FieldFullAtColumn(Team::X))));
+	}
+
+	#[test]
+	fn zobrist_hash() {
+		let mut a = Board::default();
+		let mut b = Board::default();
+		for column in [3, 2, 4, 4] {
+			a.put_tile(column, a.whos_turn()).unwrap();
+		}
+		for column in [4, 4, 3, 2] {
+			b.put_tile(column, b.whos_turn()).unwrap();
+		}
+		assert_eq!(a, b);
+		assert_eq!(a.zobrist(), b.zobrist());
+		assert_eq!(a.zobrist(), a.zobrist_hash());
+		assert_eq!(a.mirrored().zobrist(), a.mirrored().zobrist_hash());
+
+		a.pop_tile(4).unwrap();
+		assert_ne!(a.zobrist(), b.zobrist());
+		assert_eq!(a.zobrist(), a.zobrist_hash());
+		a.put_tile(4, Team::O).unwrap();
+		assert_eq!(a.zobrist(), b.zobrist());
+		assert_eq!(Board::default().zobrist(), 0);
+
+		let mut rng = StdRng::seed_from_u64(5);
+		let mut hashes = std::collections::HashMap::new();
+		for moves in 0..20 {
+			for _ in 0..50 {
+				let board = ConnectFour::random_position(moves, &mut rng);
+				assert_eq!(board.zobrist(), board.zobrist_hash());
+				let key = *hashes.entry(board.zobrist()).or_insert(board.key());
+				assert_eq!(key, board.key(), "Hash collision");
+			}
+		}
+	}
+
+	#[test]
+	fn winning_line() {
+		assert_eq!(Board::default().winning_line(), None);
+
+		let vertical = Board::from_moves("2323232").unwrap();
+		assert_eq!(vertical.winning_line(), Some((Team::X, [(1, 0), (1, 1), (1, 2), (1, 3)])));
+
+		let horizontal = Board::from_moves("4455667").unwrap();
+		assert_eq!(horizontal.winning_line(), Some((Team::X, [(3, 0), (4, 0), (5, 0), (6, 0)])));
+
+		let upwards = Board::from_moves("12234334744").unwrap();
+		assert_eq!(upwards.winning_line(), Some((Team::X, [(0, 0), (1, 1), (2, 2), (3, 3)])));
+
+		let downwards = Board::from_moves("76654554144").unwrap();
+		assert_eq!(downwards.winning_line(), Some((Team::X, [(6, 0), (5, 1), (4, 2), (3, 3)])));
+
+		check_random_games::<7, 6, 4>(4);
+	}
+
+	#[test]
+	fn is_winning_move() {
+		// X has three in a row at the bottom, O stacked three in column 0.
+		let board = Board::from_moves("213141").unwrap();
+		assert!(board.is_winning_move(4, Team::X));
+		assert!(!board.is_winning_move(4, Team::O));
+		assert!(board.is_winning_move(0, Team::O));
+		assert!(!board.is_winning_move(0, Team::X));
+		assert!(!board.is_winning_move(5, Team::X));
+		assert!(!board.is_winning_move(W, Team::X));
+		assert_eq!(board, Board::from_moves("213141").unwrap());
+
+		// Diagonal completed in the middle of the line.
+		let board = Board::from_moves("12234374644").unwrap();
+		assert!(board.is_winning_move(2, Team::X));
+		assert!(!board.is_winning_move(2, Team::O));
+
+		let mut full_column = Board::default();
+		for _ in 0..H {
+			full_column.put_tile(0, Team::X).unwrap();
+		}
+		assert!(!full_column.is_winning_move(0, Team::X));
+
+		for moves in ["2323232", "4455667", "12234334744", "76654554144"] {
+			let (rest, last) = moves.split_at(moves.len() - 1);
+			let column = last.parse::<usize>().unwrap() - 1;
+			assert!(Board::from_moves(rest).unwrap().is_winning_move(column, Team::X));
+		}
+	}
+
+	#[test]
+	fn tile_at() {
+		let board = Board::from_moves("4452").unwrap();
+		assert_eq!(board.tile_at(3, 0), Some(Team::X));
+		assert_eq!(board.tile_at(3, 1), Some(Team::O));
+		assert_eq!(board.tile_at(4, 0), Some(Team::X));
+		assert_eq!(board.tile_at(1, 0), Some(Team::O));
+		assert_eq!(board.tile_at(3, 2), None);
+		assert_eq!(board.tile_at(W, 0), None);
+		assert_eq!(board.tile_at(0, H), None);
+
+		let cells = board.iter_cells().collect::<Vec<_>>();
+		assert_eq!(cells.len(), W * H);
+		for (i, (x, y, tile)) in cells.into_iter().enumerate() {
+			assert_eq!((x, y), (i / H, i % H));
+			assert_eq!(tile, board.tile_at(x, y));
+			assert_eq!(tile, board.field()[i]);
+		}
+	}
+
+	#[test]
+	fn is_full() {
+		let mut board = Board::default();
+		for tile in 0..W * H {
+			assert!(!board.is_full());
+			assert_eq!(board.filled(), tile);
+			let column = (0..W).find(|column| board.is_legal_move(*column)).unwrap();
+			board.put_tile(column, board.whos_turn()).unwrap();
+		}
+		assert!(board.is_full());
+		assert_eq!(board.filled(), W * H);
+		assert!(board.possible_moves().is_empty());
+	}
+
+	#[test]
+	fn heuristic_2() {
+		let center = Board::from_moves("4").unwrap();
+		let edge = Board::from_moves("1").unwrap();
+		assert!(center.heuristic_2(Team::X) > edge.heuristic_2(Team::X));
+		assert!(center.heuristic_2(Team::O) < edge.heuristic_2(Team::O));
+
+		// An open three is worth more than scattered tiles.
+		let threat = Board::from_moves("17273").unwrap();
+		let scattered = Board::from_moves("17275").unwrap();
+		assert_eq!(threat.open_threes(Team::X), 1);
+		assert_eq!(scattered.open_threes(Team::X), 0);
+		assert!(threat.heuristic_2(Team::X) > scattered.heuristic_2(Team::X));
+
+		let won = Board::from_moves("1212121").unwrap();
+		assert_eq!(won.heuristic_2(Team::X), f64::MAX);
+		assert_eq!(won.heuristic_2(Team::O), f64::MIN);
+	}
+
+	/// Check for a winner by scanning all lines of `K` tiles, independent of
+	/// the bitboards.
+	fn naive_has_winner<const W: usize, const H: usize, const K: usize>(
+		board: &Board<W, H, K>,
+	) -> bool {
+		board.iter_cells().any(|(x, y, _)| {
+			[(0, 1), (1, 0), (1, 1), (-1, 1)]
+				.into_iter()
+				.any(|direction| board.line_at(x, y, direction).is_some())
+		})
+	}
+
+	/// Play random games and compare the win detection to a naive scan after
+	/// every move.
+	fn check_random_games<const W: usize, const H: usize, const K: usize>(seed: u64) {
+		let mut rng = StdRng::seed_from_u64(seed);
+		for _ in 0..200 {
+			let mut board = Board::<W, H, K>::empty();
+			loop {
+				let moves = board.possible_moves().into_iter().collect::<Vec<_>>();
+				let Some(column) = moves.choose(&mut rng) else {
+					break;
+				};
+				let team = board.whos_turn();
+				let winning = board.is_winning_move(*column, team);
+				let result = board.play(*column, team).unwrap();
+				assert_eq!(board.has_winner(), naive_has_winner(&board), "{board}");
+				assert_eq!(winning, board.has_winner());
+				assert_eq!(result, board.game_result());
+				if result.is_some() {
+					break;
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn connect_three() {
+		let mut board = Board::<7, 6, 3>::empty();
+		for column in [0, 0, 1, 1] {
+			board.put_tile(column, board.whos_turn()).unwrap();
+		}
+		assert_eq!(board.game_result(), None);
+		assert!(board.is_winning_move(2, Team::X));
+		assert_eq!(board.play(2, Team::X).unwrap(), Some(GameResult::Winner(Team::X)));
+		assert_eq!(board.winning_line(), Some((Team::X, [(0, 0), (1, 0), (2, 0)])));
+
+		// Tiles at the top of one column and the bottom of the next are no line.
+		let mut board = Board::<7, 6, 3>::empty();
+		for team in [Team::O, Team::O, Team::X, Team::O, Team::X, Team::X] {
+			board.put_tile(0, team).unwrap();
+		}
+		board.put_tile(1, Team::X).unwrap();
+		assert!(!board.has_winner());
+
+		check_random_games::<7, 6, 3>(3);
+	}
+
+	#[test]
+	fn connect_five() {
+		// 11x8 needs 99 bits per team, more than a u64.
+		let mut board = Board::<11, 8, 5>::empty();
+		for column in [0, 0, 1, 1, 2, 2, 3, 3] {
+			board.put_tile(column, board.whos_turn()).unwrap();
+		}
+		assert_eq!(board.game_result(), None);
+		assert_eq!(board.run_histogram(Team::X), [12, 0, 0, 1, 0]);
+		assert_eq!(board.threat_delta(4, Team::O), (0, -1));
+		assert_eq!(board.play(4, Team::X).unwrap(), Some(GameResult::Winner(Team::X)));
+		assert_eq!(board.winning_line().unwrap().1, [(0, 0), (1, 0), (2, 0), (3, 0), (4, 0)]);
+		assert_eq!(board.run_histogram(Team::X), [15, 0, 0, 0, 1]);
+
+		// Four in a row at the right edge doesn't win.
+		let mut board = Board::<11, 8, 5>::empty();
+		for _ in 0..4 {
+			board.put_tile(10, Team::O).unwrap();
+		}
+		assert_eq!(board.game_result_on_change(10), None);
+		assert_eq!(board.windows_through(10, 0).len(), 3);
+		assert!(board.windows_through(10, 0).iter().all(|window| window.len() == 5));
+		assert_eq!(board.play(10, Team::O).unwrap(), Some(GameResult::Winner(Team::O)));
+
+		check_random_games::<11, 8, 5>(5);
+	}
+
+	#[test]
+	fn boards_as_set_keys() {
+		let boards = ["", "4", "44", "4", "35", "53", "1234", "3214"]
+			.map(|moves| Board::from_moves(moves).unwrap());
+		let set = boards.iter().copied().collect::<HashSet<_>>();
+		assert_eq!(set.len(), 6);
+		assert!(set.contains(&Board::default()));
+
+		let sorted = boards.iter().copied().collect::<std::collections::BTreeSet<_>>();
+		assert_eq!(sorted.len(), 6);
+		let results = [GameResult::Draw, GameResult::Winner(Team::X), GameResult::Draw];
+		assert_eq!(results.into_iter().collect::<HashSet<_>>().len(), 2);
+	}
+
+	#[test]
+	fn pop_tile() {
+		let moves = [3, 3, 2, 4, 2, 2, 6, 0];
+		let mut board = Board::default();
+		let mut positions = vec![board];
+		for column in moves {
+			board.put_tile(column, board.whos_turn()).unwrap();
+			positions.push(board);
+		}
+
+		for (i, column) in moves.into_iter().enumerate().rev() {
+			assert_eq!(board.pop_tile(column).unwrap(), Team::from_turn(i));
+			assert_eq!(board, positions[i]);
+		}
+		assert_eq!(board, Board::default());
+		assert!(matches!(board.pop_tile(3), Err(Error::EmptyColumn)));
+		assert!(matches!(board.pop_tile(W), Err(Error::IndexOutOfBounds)));
+	}
+
+	#[test]
+	fn hamming_distance() {
+		let mut board = Board::default();
+		for column in [3, 2, 3, 4] {
+			board.put_tile(column, board.whos_turn()).unwrap();
+		}
+		assert_eq!(board.hamming_distance(&board), 0);
+
+		let mut other = board;
+		other.put_tile(0, Team::X).unwrap();
+		assert_eq!(board.hamming_distance(&other), 1);
+		assert_eq!(other.hamming_distance(&board), 1);
+		assert_eq!(other.hamming_distance(&Board::default()), 5);
+	}
+
+	#[test]
+	fn threat_delta() {
+		let mut board = Board::default();
+		for (column, team) in
+			[(0, Team::O), (1, Team::O), (2, Team::O), (4, Team::X), (5, Team::X), (0, Team::X)]
+		{
+			board.put_tile(column, team).unwrap();
+		}
+
+		// Creates the three in columns 3 to 5 and blocks the three in columns 0 to 2.
+		assert_eq!(board.threat_delta(3, Team::X), (1, -1));
+		// Completing the three wins, which is no open three anymore.
+		assert_eq!(board.threat_delta(3, Team::O), (-1, 0));
+		assert_eq!(board.threat_delta(6, Team::X), (1, 0));
+		assert_eq!(board.threat_delta(7, Team::X), (0, 0));
+	}
+
+	#[test]
+	fn random_position() {
+		let mut rng = StdRng::seed_from_u64(7);
+		for moves in [0, 1, 10, 30, 100] {
+			for _ in 0..20 {
+				let board = ConnectFour::random_position(moves, &mut rng);
+				let tiles = board.field().iter().filter(|tile| tile.is_some()).count();
+				assert!(tiles <= moves);
+				if tiles < moves {
+					assert!(board.game_result().is_some());
+				}
+
+				// No tile floats above an empty field.
+				for column in board.field().chunks(H) {
+					let height = column.iter().take_while(|tile| tile.is_some()).count();
+					assert!(column[height..].iter().all(Option::is_none));
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn is_blunder() {
+		let mut board = Board::default();
+		for (column, team) in [
+			(0, Team::X),
+			(1, Team::O),
+			(2, Team::X),
+			(0, Team::O),
+			(1, Team::O),
+			(2, Team::O),
+			(5, Team::X),
+			(6, Team::X),
+		] {
+			board.put_tile(column, team).unwrap();
+		}
+
+		// Filling column 3 lets O complete the second row.
+		assert!(board.is_blunder(3, Team::X));
+		assert!(!board.is_blunder(4, Team::X));
+		assert!(!board.is_blunder(7, Team::X));
+	}
 }
@@ -12,4 +12,12 @@ pub enum Error {
 	/// Field already filled at the given column.
 	#[error("Field already full at given column")]
 	FieldFullAtColumn(Team),
+
+	/// There is no tile to remove at the given column.
+	#[error("No tile to remove at given column")]
+	EmptyColumn,
+
+	/// Text could not be parsed.
+	#[error("Could not parse {0:?}")]
+	InvalidNotation(String),
 }
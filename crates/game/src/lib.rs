@@ -3,77 +3,342 @@
 
 mod board;
 mod error;
+mod outcome;
 mod player;
+mod record;
+mod snapshot;
 
 pub use self::{
-	board::{Board, GameResult, Team},
+	board::{Board, ConnectFour, GameResult, Team},
 	error::Error,
+	outcome::{EndReason, Outcome},
 	player::Player,
+	record::GameRecord,
+	snapshot::GameSnapshot,
 };
 
 /// An instance of a connect four game.
 #[derive(Debug, Clone, typed_builder::TypedBuilder)]
 pub struct Game<'a> {
-	/// Game board.
-	#[builder(setter(skip), default)]
+	/// Game board. Starts with the empty board by default, but games can also
+	/// start from any other position. The team to move is derived from the
+	/// board.
+	#[builder(default)]
 	board: Board,
 	/// Player for team X, starting player.
 	player_x: &'a dyn Player,
 	/// Player for team O, second player.
 	player_o: &'a dyn Player,
+	/// Moves played so far, in order.
+	#[builder(setter(skip), default)]
+	history: Vec<(Team, usize)>,
 }
 
 impl<'a> Game<'a> {
+	/// Restore a game from a snapshot. The players are not part of the
+	/// snapshot, so they need to be supplied again.
+	#[must_use]
+	pub fn from_snapshot(
+		snapshot: GameSnapshot,
+		player_x: &'a dyn Player,
+		player_o: &'a dyn Player,
+	) -> Self {
+		// The last move was made by the team not to move, go back from there.
+		let mut team = snapshot.to_move;
+		let mut history = snapshot
+			.history
+			.into_iter()
+			.rev()
+			.map(|column| {
+				team = team.other();
+				(team, column)
+			})
+			.collect::<Vec<_>>();
+		history.reverse();
+
+		Self { board: snapshot.board, player_x, player_o, history }
+	}
+
 	/// Return the current board position.
 	#[must_use]
 	pub fn board(&self) -> &Board {
 		&self.board
 	}
 
+	/// Return the moves played so far, in order.
+	#[must_use]
+	pub fn history(&self) -> &[(Team, usize)] {
+		&self.history
+	}
+
+	/// Return up to the last `n` moves played, in order.
+	#[must_use]
+	pub fn recent_moves(&self, n: usize) -> &[(Team, usize)] {
+		&self.history[self.history.len().saturating_sub(n)..]
+	}
+
+	/// Get the player of the given team.
+	fn player(&self, team: Team) -> &'a dyn Player {
+		match team {
+			Team::X => self.player_x,
+			Team::O => self.player_o,
+		}
+	}
+
+	/// Notify the players of the new game if no move was played yet.
+	fn start(&self) {
+		if self.history.is_empty() {
+			self.player_x.new_game();
+			self.player_o.new_game();
+		}
+	}
+
 	/// Run the game to completion using the players as actors. Returns the game
-	/// result.
+	/// result. A resigning player loses the game. Errors of the players'
+	/// [`Player::make_move_checked`], e.g. illegal moves, are returned as they
+	/// are.
 	pub fn run(&mut self) -> Result<GameResult, Error> {
+		self.start();
+		let mut team = self.board.whos_turn();
 		loop {
-			let move_x = self.player_x.make_move(&self.board, Team::X);
-			self.board.put_tile(move_x, Team::X)?;
+			let player = self.player(team);
+			if player.should_resign(&self.board, team) {
+				return Ok(GameResult::Winner(team.other()));
+			}
+			let column = player.make_move_checked(&self.board, team)?;
+			self.board.put_tile(column, team)?;
+			self.history.push((team, column));
 
-			if let Some(result) = self.board.game_result_on_change(move_x) {
+			if let Some(result) = self.board.game_result_on_change(column) {
 				return Ok(result);
 			}
 
-			let move_o = self.player_o.make_move(&self.board, Team::O);
-			self.board.put_tile(move_o, Team::O)?;
+			team = team.other();
+		}
+	}
 
-			if let Some(result) = self.board.game_result_on_change(move_o) {
-				return Ok(result);
-			}
+	/// Finish a partially played game, continuing from the current board and
+	/// turn with the configured players. Unlike [`Game::run`], this checks
+	/// whether the game is already over first and returns that result without
+	/// any further moves.
+	pub fn play_out(&mut self) -> Result<GameResult, Error> {
+		if let Some(result) = self.board.game_result() {
+			return Ok(result);
 		}
+		self.run()
 	}
 
 	/// Run the game with conversion of player errors to game loss.
 	pub fn run_error_loss(&mut self) -> GameResult {
+		self.run_with_reason().result
+	}
+
+	/// Run the game with conversion of player errors to game loss. Returns the
+	/// game result together with the reason the game ended, to be able to tell
+	/// forfeits from real wins.
+	pub fn run_with_reason(&mut self) -> Outcome {
+		self.start();
+		let mut team = self.board.whos_turn();
 		loop {
-			let move_x = self.player_x.make_move(&self.board, Team::X);
-			match self.board.put_tile(move_x, Team::X) {
-				Err(Error::FieldFullAtColumn(team)) => return GameResult::Winner(team.other()),
-				Err(err) => panic!("Player made non-game related error: {err}"),
-				Ok(_) => {}
+			let player = self.player(team);
+			if player.should_resign(&self.board, team) {
+				return Outcome {
+					result: GameResult::Winner(team.other()),
+					reason: EndReason::Resigned(team),
+				};
 			}
+			let Ok(column) = player
+				.make_move_checked(&self.board, team)
+				.and_then(|column| self.board.put_tile(column, team).map(|()| column))
+			else {
+				return Outcome {
+					result: GameResult::Winner(team.other()),
+					reason: EndReason::IllegalMove(team),
+				};
+			};
+			self.history.push((team, column));
 
-			if let Some(result) = self.board.game_result_on_change(move_x) {
-				return result;
+			match self.board.game_result_on_change(column) {
+				Some(GameResult::Draw) => {
+					return Outcome { result: GameResult::Draw, reason: EndReason::BoardFull };
+				}
+				Some(result) => return Outcome { result, reason: EndReason::Connected },
+				None => {}
 			}
 
-			let move_o = self.player_o.make_move(&self.board, Team::O);
-			match self.board.put_tile(move_o, Team::O) {
-				Err(Error::FieldFullAtColumn(team)) => return GameResult::Winner(team.other()),
-				Err(err) => panic!("Player made non-game related error: {err}"),
-				Ok(_) => {}
+			team = team.other();
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use super::*;
+
+	/// Player always playing the same column.
+	#[derive(Debug)]
+	struct ColumnPlayer(usize);
+
+	impl Player for ColumnPlayer {
+		fn make_move(&self, _board: &Board, _me: Team) -> usize {
+			self.0
+		}
+	}
+
+	#[test]
+	fn recent_moves() {
+		let (player_x, player_o) = (ColumnPlayer(0), ColumnPlayer(1));
+		let mut game = Game::builder().player_x(&player_x).player_o(&player_o).build();
+		assert!(game.recent_moves(2).is_empty());
+
+		assert_eq!(game.run_error_loss(), GameResult::Winner(Team::X));
+		assert_eq!(game.history().len(), 7);
+		assert_eq!(game.recent_moves(2), &[(Team::O, 1), (Team::X, 0)]);
+		assert_eq!(game.recent_moves(0), &[]);
+		assert_eq!(game.recent_moves(100), game.history());
+	}
+
+	/// Player playing a fixed sequence of columns, indexed by the number of
+	/// tiles on the board.
+	#[derive(Debug)]
+	struct ScriptPlayer(&'static [usize]);
+
+	impl Player for ScriptPlayer {
+		fn make_move(&self, board: &Board, _me: Team) -> usize {
+			let tiles = board.field().iter().filter(|tile| tile.is_some()).count();
+			self.0[tiles]
+		}
+	}
+
+	#[test]
+	fn end_reasons() {
+		let (player_x, player_o) = (ColumnPlayer(0), ColumnPlayer(1));
+		let mut game = Game::builder().player_x(&player_x).player_o(&player_o).build();
+		assert_eq!(
+			game.run_with_reason(),
+			Outcome { result: GameResult::Winner(Team::X), reason: EndReason::Connected }
+		);
+
+		let (player_x, player_o) = (ColumnPlayer(0), ColumnPlayer(0));
+		let mut game = Game::builder().player_x(&player_x).player_o(&player_o).build();
+		assert_eq!(
+			game.run_with_reason(),
+			Outcome {
+				result: GameResult::Winner(Team::O),
+				reason: EndReason::IllegalMove(Team::X)
 			}
+		);
 
-			if let Some(result) = self.board.game_result_on_change(move_o) {
-				return result;
+		let script = ScriptPlayer(&[
+			4, 3, 6, 0, 1, 4, 5, 5, 1, 1, 5, 0, 1, 6, 0, 1, 5, 5, 1, 0, 4, 6, 3, 2, 6, 6, 0, 4, 6,
+			5, 2, 0, 4, 2, 4, 2, 2, 2, 3, 3, 3, 3,
+		]);
+		let mut game = Game::builder().player_x(&script).player_o(&script).build();
+		assert_eq!(
+			game.run_with_reason(),
+			Outcome { result: GameResult::Draw, reason: EndReason::BoardFull }
+		);
+		assert_eq!(game.history().len(), 42);
+	}
+
+	#[test]
+	fn out_of_bounds_moves() {
+		let (player_x, player_o) = (ColumnPlayer(7), ColumnPlayer(0));
+		assert!(matches!(
+			player_x.make_move_checked(&Board::default(), Team::X),
+			Err(Error::IndexOutOfBounds)
+		));
+
+		let mut game = Game::builder().player_x(&player_x).player_o(&player_o).build();
+		assert!(matches!(game.run(), Err(Error::IndexOutOfBounds)));
+		assert!(game.history().is_empty());
+
+		let mut game = Game::builder().player_x(&player_x).player_o(&player_o).build();
+		assert_eq!(
+			game.run_with_reason(),
+			Outcome {
+				result: GameResult::Winner(Team::O),
+				reason: EndReason::IllegalMove(Team::X)
 			}
+		);
+	}
+
+	/// Player resigning right away.
+	#[derive(Debug)]
+	struct ResigningPlayer;
+
+	impl Player for ResigningPlayer {
+		fn make_move(&self, _board: &Board, _me: Team) -> usize {
+			0
+		}
+
+		fn should_resign(&self, _board: &Board, _me: Team) -> bool {
+			true
 		}
 	}
+
+	#[test]
+	fn resignation() {
+		let player_o = ColumnPlayer(1);
+		let mut game = Game::builder().player_x(&ResigningPlayer).player_o(&player_o).build();
+		assert_eq!(game.run().unwrap(), GameResult::Winner(Team::O));
+		assert!(game.history().is_empty());
+
+		let player_x = ColumnPlayer(1);
+		let mut game = Game::builder().player_x(&player_x).player_o(&ResigningPlayer).build();
+		assert_eq!(
+			game.run_with_reason(),
+			Outcome { result: GameResult::Winner(Team::X), reason: EndReason::Resigned(Team::O) }
+		);
+		assert_eq!(game.history(), &[(Team::X, 1)]);
+	}
+
+	/// Player counting the games it was notified of.
+	#[derive(Debug, Default)]
+	struct CountingPlayer(std::sync::atomic::AtomicUsize);
+
+	impl Player for CountingPlayer {
+		fn make_move(&self, board: &Board, _me: Team) -> usize {
+			(0..7).find(|column| board.is_legal_move(*column)).unwrap()
+		}
+
+		fn new_game(&self) {
+			self.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+		}
+	}
+
+	#[test]
+	fn new_game_is_announced_once() {
+		let (player_x, player_o) = (CountingPlayer::default(), ColumnPlayer(6));
+		let mut game = Game::builder().player_x(&player_x).player_o(&player_o).build();
+		game.run_error_loss();
+		assert_eq!(player_x.0.load(std::sync::atomic::Ordering::Relaxed), 1);
+
+		// Continuing a started game is not a new game.
+		let mut board = Board::default();
+		board.put_tile(0, Team::X).unwrap();
+		let snapshot = GameSnapshot { board, to_move: Team::O, history: vec![0] };
+		let mut game = Game::from_snapshot(snapshot, &player_x, &player_o);
+		game.run_error_loss();
+		assert_eq!(player_x.0.load(std::sync::atomic::Ordering::Relaxed), 1);
+	}
+
+	#[test]
+	fn play_out() {
+		let mut board = Board::default();
+		for column in [3, 0, 3, 0, 3, 0] {
+			board.put_tile(column, board.whos_turn()).unwrap();
+		}
+
+		let (player_x, player_o) = (ColumnPlayer(3), ColumnPlayer(0));
+		let mut game = Game::builder().player_x(&player_x).player_o(&player_o).board(board).build();
+		assert_eq!(game.play_out().unwrap(), GameResult::Winner(Team::X));
+		assert_eq!(game.history(), &[(Team::X, 3)]);
+
+		// Finished games are not continued.
+		assert_eq!(game.play_out().unwrap(), GameResult::Winner(Team::X));
+		assert_eq!(game.history().len(), 1);
+	}
 }
@@ -0,0 +1,25 @@
+//! Detailed outcome of a game, including why it ended.
+
+use crate::{GameResult, Team};
+
+/// Outcome of a finished game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Outcome {
+	/// Result of the game.
+	pub result: GameResult,
+	/// Reason the game ended.
+	pub reason: EndReason,
+}
+
+/// Reason a game ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EndReason {
+	/// A team connected four tiles.
+	Connected,
+	/// The board was full without a connection, the game is a draw.
+	BoardFull,
+	/// The given team made an illegal move and forfeited the game.
+	IllegalMove(Team),
+	/// The given team resigned.
+	Resigned(Team),
+}
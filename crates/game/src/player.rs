@@ -2,11 +2,108 @@
 
 use std::fmt::Debug;
 
-use crate::{board::Board, Team};
+use crate::{board::Board, Error, Team};
 
 /// Everything a player needs to play to game of connect four.
 pub trait Player: Debug {
 	/// Make a move based on the current board positions. Return the column to
 	/// put the new tile in.
 	fn make_move(&self, board: &Board, me: Team) -> usize;
+
+	/// Fallible variant of [`Player::make_move`], used by [`Game`](crate::Game)
+	/// to run the games. By default, this makes the move and checks that it is
+	/// legal, returning [`Error::IndexOutOfBounds`] or
+	/// [`Error::FieldFullAtColumn`] otherwise. Players can override it to
+	/// report internal failures instead of panicking.
+	fn make_move_checked(&self, board: &Board, me: Team) -> Result<usize, Error> {
+		let column = self.make_move(board, me);
+		let (width, _) = board.dimensions();
+		if column >= width {
+			Err(Error::IndexOutOfBounds)
+		} else if !board.is_legal_move(column) {
+			Err(Error::FieldFullAtColumn(me))
+		} else {
+			Ok(column)
+		}
+	}
+
+	/// Whether to resign instead of making a move in the current board
+	/// position, which is a loss. Checked before every move. Never resigns by
+	/// default.
+	fn should_resign(&self, _board: &Board, _me: Team) -> bool {
+		false
+	}
+
+	/// Scores of the moves the player considers in the current board position,
+	/// as pairs of column and score, where higher is better. Players that
+	/// don't score moves return `None`, which is the default. This is meant
+	/// for debugging and analysis, the scores' scale depends on the player.
+	fn scored_moves(&self, _board: &Board, _me: Team) -> Option<Vec<(usize, f64)>> {
+		None
+	}
+
+	/// Called by [`Game`](crate::Game) before the first move of a new game, so
+	/// that players keeping state across moves, e.g. a transposition table,
+	/// can reset it. Does nothing by default.
+	fn new_game(&self) {}
+
+	/// Short name of the player, e.g. to label results in evaluations and
+	/// logs. Defaults to `"unknown"`.
+	fn name(&self) -> &str {
+		"unknown"
+	}
+}
+
+impl<P: Player + ?Sized> Player for &P {
+	fn make_move(&self, board: &Board, me: Team) -> usize {
+		(**self).make_move(board, me)
+	}
+
+	fn make_move_checked(&self, board: &Board, me: Team) -> Result<usize, Error> {
+		(**self).make_move_checked(board, me)
+	}
+
+	fn should_resign(&self, board: &Board, me: Team) -> bool {
+		(**self).should_resign(board, me)
+	}
+
+	fn scored_moves(&self, board: &Board, me: Team) -> Option<Vec<(usize, f64)>> {
+		(**self).scored_moves(board, me)
+	}
+
+	fn new_game(&self) {
+		(**self).new_game();
+	}
+
+	fn name(&self) -> &str {
+		(**self).name()
+	}
+}
+
+/// Allows using boxed trait objects, e.g. to evaluate a heterogeneous set of
+/// players as `Vec<Box<dyn Player + Send + Sync>>`.
+impl<P: Player + ?Sized> Player for Box<P> {
+	fn make_move(&self, board: &Board, me: Team) -> usize {
+		(**self).make_move(board, me)
+	}
+
+	fn make_move_checked(&self, board: &Board, me: Team) -> Result<usize, Error> {
+		(**self).make_move_checked(board, me)
+	}
+
+	fn should_resign(&self, board: &Board, me: Team) -> bool {
+		(**self).should_resign(board, me)
+	}
+
+	fn scored_moves(&self, board: &Board, me: Team) -> Option<Vec<(usize, f64)>> {
+		(**self).scored_moves(board, me)
+	}
+
+	fn new_game(&self) {
+		(**self).new_game();
+	}
+
+	fn name(&self) -> &str {
+		(**self).name()
+	}
 }
@@ -0,0 +1,69 @@
+//! Record of a finished game.
+
+use std::fmt::Display;
+
+use crate::{Board, Error, GameResult, Team};
+
+/// Record of a finished game, containing everything to replay it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GameRecord {
+	/// Position the game started from.
+	pub start: Board,
+	/// Moves played from the start position, in order.
+	pub moves: Vec<(Team, usize)>,
+	/// Result of the game.
+	pub result: GameResult,
+}
+
+impl GameRecord {
+	/// Replay the recorded moves from the start position and return the final
+	/// board.
+	pub fn replay(&self) -> Result<Board, Error> {
+		let mut board = self.start;
+		for (team, column) in &self.moves {
+			board.put_tile(*column, *team)?;
+		}
+		Ok(board)
+	}
+
+	/// Write the moves in the compact move list notation of
+	/// [`Board::from_moves`], one 1-indexed column digit per move. The teams
+	/// are not part of the notation, so it only reconstructs the game if it
+	/// started from the empty board.
+	#[must_use]
+	pub fn move_string(&self) -> String {
+		self.moves.iter().map(|(_, column)| (column + 1).to_string()).collect()
+	}
+}
+
+impl Display for GameRecord {
+	/// Write the start position, the board after every move and the result.
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let mut board = self.start;
+		writeln!(f, "Start:\n{board}")?;
+		for (i, (team, column)) in self.moves.iter().enumerate() {
+			board.put_tile(*column, *team).map_err(|_| std::fmt::Error)?;
+			writeln!(f, "Move {}: {team} plays column {column}\n{board}", i + 1)?;
+		}
+		write!(f, "Result: {}", self.result)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use super::*;
+
+	#[test]
+	fn move_string_round_trip() {
+		let record = GameRecord {
+			start: Board::default(),
+			moves: vec![(Team::X, 3), (Team::O, 3), (Team::X, 0), (Team::O, 6)],
+			result: GameResult::Draw,
+		};
+		assert_eq!(record.move_string(), "4417");
+		assert_eq!(Board::from_moves(&record.move_string()).unwrap(), record.replay().unwrap());
+	}
+}
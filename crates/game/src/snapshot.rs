@@ -0,0 +1,74 @@
+//! Serializable snapshot of a running game, to save and resume it.
+
+use crate::{Board, Game, Team};
+
+/// Snapshot of a game in progress. Players are not part of the snapshot, they
+/// are supplied again when restoring the game via [`Game::from_snapshot`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GameSnapshot {
+	/// Current board position.
+	pub board: Board,
+	/// The team to make the next move.
+	pub to_move: Team,
+	/// Columns of the moves played so far, in order.
+	pub history: Vec<usize>,
+}
+
+impl<'a> From<&Game<'a>> for GameSnapshot {
+	fn from(game: &Game<'a>) -> Self {
+		Self {
+			board: *game.board(),
+			to_move: game.board().whos_turn(),
+			history: game.history().iter().map(|(_team, column)| *column).collect(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use super::*;
+	use crate::{GameResult, Player};
+
+	/// Player always playing the same column.
+	#[derive(Debug)]
+	struct ColumnPlayer(usize);
+
+	impl Player for ColumnPlayer {
+		fn make_move(&self, _board: &Board, _me: Team) -> usize {
+			self.0
+		}
+	}
+
+	#[test]
+	fn snapshot_round_trip() {
+		let mut board = Board::default();
+		board.put_tile(3, Team::X).unwrap();
+		board.put_tile(3, Team::O).unwrap();
+		board.put_tile(4, Team::X).unwrap();
+		let snapshot = GameSnapshot { board, to_move: Team::O, history: vec![3, 3, 4] };
+
+		let (player_x, player_o) = (ColumnPlayer(0), ColumnPlayer(6));
+		let game = Game::from_snapshot(snapshot.clone(), &player_x, &player_o);
+		assert_eq!(game.board(), &board);
+		assert_eq!(game.board().whos_turn(), Team::O);
+		assert_eq!(game.history(), &[(Team::X, 3), (Team::O, 3), (Team::X, 4)]);
+		assert_eq!(GameSnapshot::from(&game), snapshot);
+	}
+
+	#[test]
+	fn resumed_game_continues_with_right_team() {
+		let mut board = Board::default();
+		board.put_tile(3, Team::X).unwrap();
+		board.put_tile(3, Team::O).unwrap();
+		board.put_tile(4, Team::X).unwrap();
+		let snapshot = GameSnapshot { board, to_move: Team::O, history: vec![3, 3, 4] };
+
+		let (player_x, player_o) = (ColumnPlayer(0), ColumnPlayer(6));
+		let mut game = Game::from_snapshot(snapshot, &player_x, &player_o);
+		assert_eq!(game.run().unwrap(), GameResult::Winner(Team::O));
+		assert_eq!(game.history()[3], (Team::O, 6));
+	}
+}
@@ -0,0 +1,50 @@
+//! Total comparison of heuristic values, robust against NaN values from
+//! misbehaving heuristics or models.
+
+use std::cmp::Ordering;
+
+/// Compare values for maximization, treating NaN as the worst value, i.e.
+/// smaller than any other value. This way NaN values are never selected by
+/// `max_by` unless all values are NaN.
+#[allow(clippy::trivially_copy_pass_by_ref)] // Comparator signature of `max_by` and `sort_by`.
+pub(crate) fn nan_worst_for_max(a: &f64, b: &f64) -> Ordering {
+	match (a.is_nan(), b.is_nan()) {
+		(true, true) => Ordering::Equal,
+		(true, false) => Ordering::Less,
+		(false, true) => Ordering::Greater,
+		(false, false) => a.total_cmp(b),
+	}
+}
+
+/// Compare values for minimization, treating NaN as the worst value, i.e.
+/// bigger than any other value. This way NaN values are never selected by
+/// `min_by` unless all values are NaN.
+#[allow(clippy::trivially_copy_pass_by_ref)] // Comparator signature of `min_by` and `sort_by`.
+pub(crate) fn nan_worst_for_min(a: &f64, b: &f64) -> Ordering {
+	match (a.is_nan(), b.is_nan()) {
+		(true, true) => Ordering::Equal,
+		(true, false) => Ordering::Greater,
+		(false, true) => Ordering::Less,
+		(false, false) => a.total_cmp(b),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use super::*;
+
+	#[test]
+	fn nan_is_never_selected() {
+		let values = [1.0, f64::NAN, -1.0];
+		assert_eq!(values.into_iter().max_by(nan_worst_for_max), Some(1.0));
+		assert_eq!(values.into_iter().min_by(nan_worst_for_min), Some(-1.0));
+		assert!(values
+			.into_iter()
+			.filter(|value| value.is_nan())
+			.max_by(nan_worst_for_max)
+			.unwrap()
+			.is_nan());
+	}
+}
@@ -0,0 +1,79 @@
+//! Encodings of the board as input for the neural network models.
+
+use game::{Board, Team};
+
+/// Encoding of the board as network input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InputEncoding {
+	/// A single plane with 1 for own tiles, -1 for opponent tiles and 0 for
+	/// empty fields. Used by older models.
+	#[default]
+	SinglePlane,
+	/// Two binary planes, the first one marking own tiles, the second one
+	/// marking opponent tiles.
+	TwoPlanes,
+}
+
+impl InputEncoding {
+	/// Number of input planes/channels of this encoding.
+	#[must_use]
+	pub fn planes(self) -> usize {
+		match self {
+			Self::SinglePlane => 1,
+			Self::TwoPlanes => 2,
+		}
+	}
+
+	/// Get the encoding from the number of input planes.
+	#[must_use]
+	pub fn from_planes(planes: usize) -> Self {
+		match planes {
+			1 => Self::SinglePlane,
+			2 => Self::TwoPlanes,
+			_ => panic!("Unsupported number of input planes: {planes}"),
+		}
+	}
+
+	/// Encode the board from the view of `me`. The planes are concatenated,
+	/// each plane being in the order of [`Board::field`], i.e. column-major.
+	/// The single plane encoding is [`Board::to_input_plane`].
+	#[must_use]
+	pub fn encode(self, board: &Board, me: Team) -> Vec<f32> {
+		match self {
+			Self::SinglePlane => board.to_input_plane(me),
+			Self::TwoPlanes => [Some(me), Some(me.other())]
+				.into_iter()
+				.flat_map(|plane_team| {
+					board
+						.iter_cells()
+						.map(move |(_, _, tile)| if tile == plane_team { 1.0 } else { 0.0 })
+				})
+				.collect(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used, clippy::float_cmp)]
+
+	use super::*;
+
+	#[test]
+	fn input_plane_matches_single_plane_encoding() {
+		let mut board = Board::default();
+		for column in [3, 3, 0, 6] {
+			board.put_tile(column, board.whos_turn()).unwrap();
+		}
+
+		let plane = board.to_input_plane(Team::O);
+		assert_eq!(plane, InputEncoding::SinglePlane.encode(&board, Team::O));
+		let (_, height) = board.dimensions();
+		let at = |x: usize, y: usize| plane[x * height + y];
+		assert_eq!(at(3, 0), -1.0);
+		assert_eq!(at(3, 1), 1.0);
+		assert_eq!(at(0, 0), -1.0);
+		assert_eq!(at(6, 0), 1.0);
+		assert_eq!(plane.iter().filter(|value| **value == 0.0).count(), plane.len() - 4);
+	}
+}
@@ -0,0 +1,117 @@
+//! Ensemble player, combining multiple players by majority vote.
+
+use game::{Board, Player, Team};
+
+/// Ensemble player, asking every member for its move and playing the legal
+/// column with the most (weighted) votes. Ties are broken center-first.
+/// Illegal votes are ignored.
+#[derive(Debug)]
+pub struct EnsemblePlayer {
+	/// Members of the ensemble and their voting weights.
+	members: Vec<(Box<dyn Player + Send + Sync>, f64)>,
+}
+
+impl EnsemblePlayer {
+	/// Create a new ensemble where every member's vote counts the same.
+	#[must_use]
+	pub fn new(members: Vec<Box<dyn Player + Send + Sync>>) -> Self {
+		Self::weighted(members.into_iter().map(|member| (member, 1.0)).collect())
+	}
+
+	/// Create a new ensemble where every member's vote counts with the given
+	/// weight.
+	#[must_use]
+	pub fn weighted(members: Vec<(Box<dyn Player + Send + Sync>, f64)>) -> Self {
+		Self { members }
+	}
+
+	/// Collect the weighted votes of the members for each column.
+	fn votes(&self, board: &Board, me: Team) -> Vec<f64> {
+		let (width, _) = board.dimensions();
+		let mut votes = vec![0.0; width];
+		for (member, weight) in &self.members {
+			let column = member.make_move(board, me);
+			if board.is_legal_move(column) {
+				votes[column] += weight;
+			}
+		}
+		votes
+	}
+}
+
+impl Player for EnsemblePlayer {
+	fn make_move(&self, board: &Board, me: Team) -> usize {
+		let votes = self.votes(board, me);
+		board
+			.moves_center_ordered()
+			.into_iter()
+			.reduce(|best, column| if votes[column] > votes[best] { column } else { best })
+			.expect("No possible moves")
+	}
+
+	fn scored_moves(&self, board: &Board, me: Team) -> Option<Vec<(usize, f64)>> {
+		let votes = self.votes(board, me);
+		let mut scores = board
+			.moves_center_ordered()
+			.into_iter()
+			.map(|column| (column, votes[column]))
+			.collect::<Vec<_>>();
+		scores.sort_unstable_by_key(|(column, _)| *column);
+		Some(scores)
+	}
+
+	fn new_game(&self) {
+		for (member, _) in &self.members {
+			member.new_game();
+		}
+	}
+
+	fn name(&self) -> &str {
+		"ensemble"
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Player always playing the same column.
+	#[derive(Debug)]
+	struct ColumnPlayer(usize);
+
+	impl Player for ColumnPlayer {
+		fn make_move(&self, _board: &Board, _me: Team) -> usize {
+			self.0
+		}
+	}
+
+	#[test]
+	fn majority_wins() {
+		let board = Board::default();
+		let agreeing =
+			EnsemblePlayer::new(vec![Box::new(ColumnPlayer(1)), Box::new(ColumnPlayer(1))]);
+		assert_eq!(agreeing.make_move(&board, Team::X), 1);
+
+		let dissenting = EnsemblePlayer::new(vec![
+			Box::new(ColumnPlayer(5)),
+			Box::new(ColumnPlayer(1)),
+			Box::new(ColumnPlayer(1)),
+		]);
+		assert_eq!(dissenting.make_move(&board, Team::X), 1);
+
+		// Weights can overturn the majority, ties go to the center.
+		let weighted = EnsemblePlayer::weighted(vec![
+			(Box::new(ColumnPlayer(5)), 3.0),
+			(Box::new(ColumnPlayer(1)), 1.0),
+			(Box::new(ColumnPlayer(1)), 1.0),
+		]);
+		assert_eq!(weighted.make_move(&board, Team::X), 5);
+		let tied = EnsemblePlayer::new(vec![Box::new(ColumnPlayer(1)), Box::new(ColumnPlayer(4))]);
+		assert_eq!(tied.make_move(&board, Team::X), 4);
+
+		// Illegal votes don't count.
+		let illegal =
+			EnsemblePlayer::new(vec![Box::new(ColumnPlayer(9)), Box::new(ColumnPlayer(9))]);
+		assert_eq!(illegal.make_move(&board, Team::X), 3);
+	}
+}
@@ -1,36 +1,126 @@
 //! Terminal IO player.
-#![allow(clippy::print_stdout)]
 
-use std::io::{IsTerminal, Write};
+use std::{
+	fmt::Debug,
+	io::{BufRead, BufReader, IsTerminal, Stdin, Stdout, Write},
+	sync::Mutex,
+};
 
 use game::{Board, Player, Team};
 
-/// Terminal IO player.
-#[derive(Debug)]
-pub struct IoPlayer;
+/// IO player, reading the moves from the input and writing the board and
+/// prompts to the output. Defaults to the terminal, i.e. stdin and stdout.
+pub struct IoPlayer<R = BufReader<Stdin>, W = Stdout> {
+	/// Input to read the moves from.
+	reader: Mutex<R>,
+	/// Output to write the board and prompts to.
+	writer: Mutex<W>,
+	/// Whether to write the board with colors.
+	colored: bool,
+}
+
+impl IoPlayer {
+	/// Create a new IO player on the terminal, i.e. stdin and stdout. The
+	/// board is colored if stdout is a terminal.
+	#[must_use]
+	pub fn stdio() -> Self {
+		let stdout = std::io::stdout();
+		let colored = stdout.is_terminal();
+		Self::new(BufReader::new(std::io::stdin()), stdout).with_colors(colored)
+	}
+}
+
+impl Default for IoPlayer {
+	fn default() -> Self {
+		Self::stdio()
+	}
+}
+
+impl<R: BufRead, W: Write> IoPlayer<R, W> {
+	/// Create a new IO player reading from the reader and writing to the
+	/// writer. The board is written without colors, see
+	/// [`IoPlayer::with_colors`].
+	#[must_use]
+	pub fn new(reader: R, writer: W) -> Self {
+		Self { reader: Mutex::new(reader), writer: Mutex::new(writer), colored: false }
+	}
+
+	/// Set whether to write the board with colors, which only makes sense if
+	/// the writer is a terminal.
+	#[must_use]
+	pub fn with_colors(mut self, colored: bool) -> Self {
+		self.colored = colored;
+		self
+	}
+
+	/// Get the writer back, e.g. to inspect the output.
+	#[must_use]
+	pub fn into_writer(self) -> W {
+		self.writer.into_inner().expect("lock poisened")
+	}
+}
 
-impl Player for IoPlayer {
+impl<R, W> Debug for IoPlayer<R, W> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("IoPlayer")
+			.field("reader", &"<reader>")
+			.field("writer", &"<writer>")
+			.field("colored", &self.colored)
+			.finish()
+	}
+}
+
+impl<R: BufRead, W: Write> Player for IoPlayer<R, W> {
 	fn make_move(&self, board: &Board, me: Team) -> usize {
-		if std::io::stdout().is_terminal() {
-			println!("Current board:\n{}", board.colored_string(me));
+		let mut reader = self.reader.lock().expect("lock poisened");
+		let mut writer = self.writer.lock().expect("lock poisened");
+		if self.colored {
+			writeln!(writer, "Current board:\n{}", board.colored_string(me)).expect("write output");
 		} else {
-			println!("Current board:\n{board}");
+			writeln!(writer, "Current board:\n{board}").expect("write output");
 		}
-		println!("0 | 1 | 2 | 3 | 4 | 5 | 6 \n");
+		writeln!(writer, "0 | 1 | 2 | 3 | 4 | 5 | 6 \n").expect("write output");
 
-		let possible_moves = board.possible_moves();
 		loop {
-			print!("Enter number column to place tile in: ");
-			std::io::stdout().flush().expect("flush STDOUT");
+			write!(writer, "Enter number column to place tile in: ").expect("write output");
+			writer.flush().expect("flush output");
 
 			let mut input = String::new();
-			std::io::stdin().read_line(&mut input).expect("read STDIO");
+			let read = reader.read_line(&mut input).expect("read input");
+			assert!(read > 0, "Input ended before a move was entered");
 			match input.trim().parse::<usize>() {
-				Ok(column) if possible_moves.contains(&column) => break column,
+				Ok(column) if board.is_legal_move(column) => break column,
 				_ => {
-					println!("Invalid move, try again!");
+					writeln!(writer, "Invalid move, try again!").expect("write output");
 				}
 			}
 		}
 	}
+
+	fn name(&self) -> &str {
+		"io"
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use super::*;
+
+	#[test]
+	fn scripted_input() {
+		let mut board = Board::default();
+		for _ in 0..6 {
+			board.put_tile(2, board.whos_turn()).unwrap();
+		}
+
+		let player = IoPlayer::new("abc\n2\n4\n".as_bytes(), Vec::new());
+		assert_eq!(player.make_move(&board, Team::X), 4);
+
+		let output = String::from_utf8(player.into_writer()).unwrap();
+		assert!(output.starts_with(&format!("Current board:\n{board}\n")));
+		assert_eq!(output.matches("Invalid move, try again!").count(), 2);
+		assert_eq!(output.matches("Enter number column to place tile in: ").count(), 3);
+	}
 }
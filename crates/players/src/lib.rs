@@ -1,15 +1,31 @@
 //! Connect four game player implementations.
 #![allow(clippy::expect_used)]
 
+mod compare;
+mod encoding;
+mod ensemble;
 mod io;
+mod logging;
 mod minimax;
 mod policy_conv_nn;
 mod random;
+mod solver;
+mod table;
+mod timed;
 mod value_conv_nn;
 
 pub use burn::backend::{NdArrayBackend, WgpuBackend};
 
 pub use self::{
-	io::IoPlayer, minimax::MinimaxPlayer, policy_conv_nn::AiPolicyPlayer, random::RandomPlayer,
-	value_conv_nn::AiValuePlayer,
+	encoding::InputEncoding,
+	ensemble::EnsemblePlayer,
+	io::IoPlayer,
+	logging::{LoggingPlayer, MoveLog},
+	minimax::{MinimaxPlayer, SearchStats},
+	policy_conv_nn::AiPolicyPlayer,
+	random::{RandomPlayer, SeededRandomPlayer},
+	solver::{PositionClass, SolverPlayer},
+	table::{MoveTable, TableLookupPlayer},
+	timed::TimedPlayer,
+	value_conv_nn::{AiValuePlayer, ValueNetConfig},
 };
@@ -0,0 +1,146 @@
+//! Player wrapper logging every decision for debugging.
+#![allow(clippy::print_stdout)]
+
+use std::fmt::Debug;
+
+use game::{Board, Player, Team};
+
+/// Log entry of a single decision of a [`LoggingPlayer`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoveLog {
+	/// Board position the move was made in.
+	pub board: Board,
+	/// The team that made the move.
+	pub me: Team,
+	/// The chosen column.
+	pub column: usize,
+	/// Scores of the considered moves, if the player scores its moves, see
+	/// [`Player::scored_moves`].
+	pub scores: Option<Vec<(usize, f64)>>,
+}
+
+/// Callback to receive the move logs.
+type LogFn = Box<dyn Fn(&MoveLog) + Send + Sync>;
+
+/// Player wrapper logging every decision of the inner player, including the
+/// move scores if the inner player provides them. Note that getting the
+/// scores usually means searching the position a second time.
+pub struct LoggingPlayer<P> {
+	/// Player making the decisions.
+	inner: P,
+	/// Callback receiving the logs.
+	log: LogFn,
+}
+
+impl<P> LoggingPlayer<P> {
+	/// Wrap the player to print its decisions to STDOUT.
+	#[must_use]
+	pub fn new(inner: P) -> Self {
+		Self::with_callback(inner, |log| {
+			println!("Board ({:?} to move):\n{}", log.me, log.board);
+			if let Some(scores) = &log.scores {
+				for (column, score) in scores {
+					println!("Column {column}: {score}");
+				}
+			}
+			println!("Chose column {}\n", log.column);
+		})
+	}
+
+	/// Wrap the player to send its decisions to the callback.
+	#[must_use]
+	pub fn with_callback(inner: P, log: impl Fn(&MoveLog) + Send + Sync + 'static) -> Self {
+		Self { inner, log: Box::new(log) }
+	}
+
+	/// Get the inner player.
+	#[must_use]
+	pub fn inner(&self) -> &P {
+		&self.inner
+	}
+}
+
+impl<P: Player> Player for LoggingPlayer<P> {
+	fn make_move(&self, board: &Board, me: Team) -> usize {
+		let scores = self.inner.scored_moves(board, me);
+		let column = self.inner.make_move(board, me);
+		(self.log)(&MoveLog { board: *board, me, column, scores });
+		column
+	}
+
+	fn should_resign(&self, board: &Board, me: Team) -> bool {
+		self.inner.should_resign(board, me)
+	}
+
+	fn scored_moves(&self, board: &Board, me: Team) -> Option<Vec<(usize, f64)>> {
+		self.inner.scored_moves(board, me)
+	}
+
+	fn new_game(&self) {
+		self.inner.new_game();
+	}
+
+	fn name(&self) -> &str {
+		self.inner.name()
+	}
+}
+
+impl<P: Debug> Debug for LoggingPlayer<P> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("LoggingPlayer").field("inner", &self.inner).field("log", &"<fn>").finish()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use std::sync::{Arc, Mutex};
+
+	use super::*;
+	use crate::SolverPlayer;
+
+	/// Player playing the first legal column.
+	#[derive(Debug)]
+	struct FirstColumnPlayer;
+
+	impl Player for FirstColumnPlayer {
+		fn make_move(&self, board: &Board, _me: Team) -> usize {
+			(0..7).find(|column| board.is_legal_move(*column)).unwrap()
+		}
+	}
+
+	#[test]
+	fn logs_decisions() {
+		let logs = Arc::new(Mutex::new(Vec::new()));
+		let recorded = Arc::clone(&logs);
+		let player = LoggingPlayer::with_callback(FirstColumnPlayer, move |log| {
+			recorded.lock().unwrap().push(log.clone());
+		});
+
+		let mut board = Board::default();
+		for _ in 0..6 {
+			board.put_tile(0, Team::O).unwrap();
+		}
+		assert_eq!(player.make_move(&board, Team::X), 1);
+
+		let logs = logs.lock().unwrap();
+		assert_eq!(*logs, [MoveLog { board, me: Team::X, column: 1, scores: None }]);
+	}
+
+	#[test]
+	fn logs_scores_of_scoring_players() {
+		let logs = Arc::new(Mutex::new(Vec::new()));
+		let recorded = Arc::clone(&logs);
+		let player = LoggingPlayer::with_callback(SolverPlayer::new(2), move |log| {
+			recorded.lock().unwrap().push(log.clone());
+		});
+
+		player.make_move(&Board::default(), Team::X);
+		let scores = logs.lock().unwrap()[0].scores.clone().unwrap();
+		assert_eq!(
+			scores.iter().map(|(column, _)| *column).collect::<Vec<_>>(),
+			[0, 1, 2, 3, 4, 5, 6]
+		);
+	}
+}
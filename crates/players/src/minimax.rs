@@ -1,27 +1,345 @@
 //! Minimax player implementation using heuristics and recursive min-maxing.
 
-use std::fmt::Debug;
+use std::{
+	collections::HashMap,
+	fmt::Debug,
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Mutex,
+	},
+	time::{Duration, Instant},
+};
 
 use game::{Board, GameResult, Player, Team};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::compare::{nan_worst_for_max, nan_worst_for_min};
 
 /// Type for heuristic function.
 type HeuristicFn<'a> = &'a (dyn Fn(&Board, Team) -> f64 + Send + Sync);
 
+/// Kind of value stored in the transposition table, as alpha-beta pruning
+/// only bounds the values of pruned positions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bound {
+	/// The exact value.
+	Exact,
+	/// The value is at least the stored value.
+	Lower,
+	/// The value is at most the stored value.
+	Upper,
+}
+
+/// Search result stored in the transposition table.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SearchEntry {
+	/// Exact key of the position, see [`Board::key`], to detect collisions of
+	/// the Zobrist hashes.
+	key: u64,
+	/// The values `(value, opponent_value)` of the position.
+	values: (f64, f64),
+	/// Whether the value is exact or a bound.
+	bound: Bound,
+}
+
+/// Transposition table caching heuristic values and search results of
+/// positions.
+#[derive(Debug, Default)]
+struct TranspositionTable {
+	/// Heuristic values `(value, opponent_value)` by board key and evaluating
+	/// team.
+	heuristic: HashMap<(u64, Team), (f64, f64)>,
+	/// Search results by Zobrist hash, evaluating team and remaining search
+	/// depth. Results of other depths differ from the search's, so they are
+	/// not reused. The entries hold the exact board key, as different
+	/// positions can have the same hash.
+	searches: HashMap<(u64, Team, usize), SearchEntry>,
+}
+
+impl TranspositionTable {
+	/// Remove all entries.
+	fn clear(&mut self) {
+		self.heuristic.clear();
+		self.searches.clear();
+	}
+}
+
+/// Statistics of a search.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchStats {
+	/// Number of non-terminal positions visited in the search tree, excluding
+	/// the root.
+	pub nodes_visited: usize,
+	/// Maximum depth in plies reached in the search tree.
+	pub max_depth_reached: usize,
+	/// Time the search took.
+	pub elapsed: Duration,
+	/// Deepness the moves were searched to. For timed searches, this is the
+	/// deepest completed iteration, 0 if not even the first one completed.
+	pub deepness: usize,
+}
+
+/// Limits of a single search.
+#[derive(Debug, Clone, Copy)]
+struct SearchLimits {
+	/// Deepness to search to.
+	deepness: usize,
+	/// Time at which the search is aborted, if any.
+	deadline: Option<Instant>,
+}
+
+impl SearchLimits {
+	/// Whether the deadline passed, making the search results invalid.
+	fn expired(&self) -> bool {
+		self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+	}
+}
+
 /// Minimax player with a custom heuristic.
 pub struct MinimaxPlayer<'a> {
-	/// Deepness to do minimax search to.
+	/// Deepness to do minimax search to. The maximum deepness if there is a
+	/// time budget.
 	deepness: usize,
+	/// Optional time budget per move, searching with iterative deepening, see
+	/// [`MinimaxPlayer::new_timed`].
+	budget: Option<Duration>,
 	/// Heuristic function to compute the value of board positions. 0.0 should
 	/// be a draw, anything above is winning, below zero is losing position. The
 	/// strength of is shown by the absolute number.
 	heuristic: HeuristicFn<'a>,
+	/// Optional heuristic the opponent is assumed to use, see
+	/// [`MinimaxPlayer::with_opponent_heuristic`].
+	opponent_heuristic: Option<HeuristicFn<'a>>,
+	/// Optional randomization of the move selection: the epsilon within which
+	/// moves count as equally good and the random number generator to pick
+	/// among them.
+	randomization: Option<(f64, Mutex<StdRng>)>,
+	/// Optional beam width, the maximum number of moves to search at each
+	/// node.
+	beam_width: Option<usize>,
+	/// Number of positions visited in the current search.
+	nodes_visited: AtomicUsize,
+	/// Maximum depth reached in the current search.
+	max_depth_reached: AtomicUsize,
+	/// Statistics of the last search.
+	last_search_stats: Mutex<SearchStats>,
+	/// Optional transposition table caching the heuristic values and search
+	/// results of positions, kept until the next game starts.
+	table: Option<Mutex<TranspositionTable>>,
+	/// Whether to prune the search tree with alpha-beta pruning, see
+	/// [`MinimaxPlayer::without_pruning`].
+	pruning: bool,
+	/// Whether to search the moves from the center outwards, see
+	/// [`MinimaxPlayer::without_move_ordering`].
+	move_ordering: bool,
 }
 
 impl<'a> MinimaxPlayer<'a> {
 	/// Create new minimax player with custom heuristic.
 	#[must_use]
 	pub fn new(deepness: usize, heuristic: HeuristicFn<'a>) -> Self {
-		Self { deepness, heuristic }
+		Self {
+			deepness,
+			budget: None,
+			heuristic,
+			opponent_heuristic: None,
+			randomization: None,
+			beam_width: None,
+			nodes_visited: AtomicUsize::new(0),
+			max_depth_reached: AtomicUsize::new(0),
+			last_search_stats: Mutex::new(SearchStats::default()),
+			table: None,
+			pruning: true,
+			move_ordering: true,
+		}
+	}
+
+	/// Create new minimax player with custom heuristic, that picks uniformly
+	/// at random among all moves with a value within `epsilon` of the best
+	/// move's value. The random choice is reproducible via the seed.
+	#[must_use]
+	pub fn new_randomized(
+		deepness: usize,
+		heuristic: HeuristicFn<'a>,
+		epsilon: f64,
+		seed: u64,
+	) -> Self {
+		Self {
+			randomization: Some((epsilon, Mutex::new(StdRng::seed_from_u64(seed)))),
+			..Self::new(deepness, heuristic)
+		}
+	}
+
+	/// Create new minimax player with custom heuristic, searching as deep as
+	/// possible within the time budget per move. The moves are searched with
+	/// iterative deepening, to depth 1, 2, 3 and so on, starting with the best
+	/// move of the previous iteration. When the budget is used up, the running
+	/// iteration is aborted and the best move of the deepest completed one is
+	/// played. If not even the first iteration completes, the most central
+	/// legal move is played.
+	#[must_use]
+	pub fn new_timed(budget: Duration, heuristic: HeuristicFn<'a>) -> Self {
+		Self { budget: Some(budget), ..Self::new(usize::MAX, heuristic) }
+	}
+
+	/// Restrict the search to the `beam_width` best-looking moves at each
+	/// node, judged by the heuristic value of the position after the move.
+	/// This allows deeper searches with the same budget, but sacrifices
+	/// optimality, as good moves that look bad at first are never searched.
+	#[must_use]
+	pub fn with_beam_width(mut self, beam_width: usize) -> Self {
+		self.beam_width = Some(beam_width);
+		self
+	}
+
+	/// Model the opponent as using a different heuristic, e.g. to exploit a
+	/// known weak opponent. The heuristic values positions from our
+	/// perspective, like the main heuristic, and the opponent is assumed to
+	/// minimize it. Min nodes choose their move by this heuristic instead of
+	/// ours, while the value of the chosen move is still our own evaluation.
+	/// Defaults to the main heuristic, which is plain minimax.
+	#[must_use]
+	pub fn with_opponent_heuristic(mut self, opponent_heuristic: HeuristicFn<'a>) -> Self {
+		self.opponent_heuristic = Some(opponent_heuristic);
+		self
+	}
+
+	/// Cache the heuristic values and search results of positions in a
+	/// transposition table, so that positions reached again, e.g. by
+	/// transposed moves, are not evaluated or searched again. The positions of
+	/// a game only grow, so later moves of the same game reuse the entries of
+	/// earlier moves. The table is cleared when a new game starts, see
+	/// [`Player::new_game`]. The moves are the same as without the table.
+	#[must_use]
+	pub fn with_transposition_table(mut self) -> Self {
+		self.table = Some(Mutex::new(TranspositionTable::default()));
+		self
+	}
+
+	/// Disable alpha-beta pruning and search the full tree. Pruning never
+	/// changes the move values, so this is only useful to compare against the
+	/// full search. Pruning is always disabled with an opponent heuristic, as
+	/// the min nodes then choose by a different value than the one the bounds
+	/// are about.
+	#[must_use]
+	pub fn without_pruning(mut self) -> Self {
+		self.pruning = false;
+		self
+	}
+
+	/// Search the moves from left to right, i.e. `0, 1, 2, 3, 4, 5, 6` on the
+	/// standard board, instead of from the center outwards in the order of
+	/// [`Board::possible_moves_ordered`]. Central moves tend to be stronger, so
+	/// searching them first lets alpha-beta pruning cut off more of the tree.
+	/// The move values are the same either way, so this is only useful to
+	/// compare against the ordered search. Ties between equally good moves are
+	/// broken by the order, the first move wins.
+	#[must_use]
+	pub fn without_move_ordering(mut self) -> Self {
+		self.move_ordering = false;
+		self
+	}
+
+	/// Whether the search prunes the tree with alpha-beta pruning.
+	fn prunes(&self) -> bool {
+		self.pruning && self.opponent_heuristic.is_none()
+	}
+
+	/// Evaluate a position with both our and the opponent's heuristic, as
+	/// `(value, opponent_value)`. Uses the transposition table if there is
+	/// one.
+	fn evaluate(&self, board: &Board, me: Team) -> (f64, f64) {
+		let Some(table) = &self.table else {
+			return self.heuristic_values(board, me);
+		};
+		let key = (board.key(), me);
+		if let Some(values) = table.lock().expect("lock poisened").heuristic.get(&key) {
+			return *values;
+		}
+		let values = self.heuristic_values(board, me);
+		table.lock().expect("lock poisened").heuristic.insert(key, values);
+		values
+	}
+
+	/// Look up the search result of a position with the remaining depth in the
+	/// transposition table. Bounds are only returned if they are outside of the
+	/// window `[alpha, beta]`, as the search would be cut off then anyway.
+	fn lookup_search(
+		&self,
+		board: &Board,
+		me: Team,
+		remaining: usize,
+		alpha: f64,
+		beta: f64,
+	) -> Option<(f64, f64)> {
+		let table = self.table.as_ref()?.lock().expect("lock poisened");
+		let entry = table
+			.searches
+			.get(&(board.zobrist(), me, remaining))
+			.filter(|entry| entry.key == board.key())?;
+		match entry.bound {
+			Bound::Exact => Some(entry.values),
+			Bound::Lower if entry.values.0 > beta => Some(entry.values),
+			Bound::Upper if entry.values.0 < alpha => Some(entry.values),
+			_ => None,
+		}
+	}
+
+	/// Store the search result of a position with the remaining depth in the
+	/// transposition table, if there is one. The result was searched with the
+	/// window `[alpha, beta]`, values outside of it are only bounds. Results of
+	/// aborted searches are not stored.
+	fn store_search(
+		&self,
+		limits: &SearchLimits,
+		board: &Board,
+		me: Team,
+		remaining: usize,
+		(alpha, beta): (f64, f64),
+		values: (f64, f64),
+	) {
+		let Some(table) = &self.table else {
+			return;
+		};
+		if limits.expired() {
+			return;
+		}
+		let bound = if values.0 < alpha {
+			Bound::Upper
+		} else if values.0 > beta {
+			Bound::Lower
+		} else {
+			Bound::Exact
+		};
+		table.lock().expect("lock poisened").searches.insert(
+			(board.zobrist(), me, remaining),
+			SearchEntry { key: board.key(), values, bound },
+		);
+	}
+
+	/// Compute the values of a position with both our and the opponent's
+	/// heuristic, as `(value, opponent_value)`.
+	fn heuristic_values(&self, board: &Board, me: Team) -> (f64, f64) {
+		let value = (self.heuristic)(board, me);
+		match self.opponent_heuristic {
+			Some(opponent_heuristic) => (value, opponent_heuristic(board, me)),
+			None => (value, value),
+		}
+	}
+
+	/// Get the statistics of the last search done in
+	/// [`make_move`](Player::make_move) or
+	/// [`evaluate_moves`](MinimaxPlayer::evaluate_moves). The statistics are
+	/// mixed up if multiple searches run concurrently on the same player.
+	#[must_use]
+	pub fn last_search_stats(&self) -> SearchStats {
+		*self.last_search_stats.lock().expect("lock poisened")
+	}
+
+	/// Count a visited position at the given depth for the search statistics.
+	fn visit(&self, depth: usize) {
+		self.nodes_visited.fetch_add(1, Ordering::Relaxed);
+		self.max_depth_reached.fetch_max(depth, Ordering::Relaxed);
 	}
 
 	/// Create new minimax player with heuristic 1.
@@ -30,93 +348,321 @@ impl<'a> MinimaxPlayer<'a> {
 		Self::new(deepness, &Board::heuristic_1)
 	}
 
-	/// Our turn, take the best value out of our turns.
-	fn max_value(&self, board: &Board, me: Team, current_deepness: usize) -> f64 {
-		if current_deepness + 1 < self.deepness {
-			board
-				.possible_moves()
-				.into_iter()
-				.map(|column| {
-					let mut test_board = *board;
-					test_board.put_tile(column, me).expect("Possible move was in fact impossible");
-
-					match test_board.game_result_on_change(column) {
-						Some(GameResult::Draw) => return 0.0,
-						Some(GameResult::Winner(team)) => {
-							return if team == me { f64::MAX } else { f64::MIN }
+	/// Get the moves to search when `team` is to move, in search order. These
+	/// are all possible moves, or only the best-looking moves for `team` if the
+	/// beam width is set. The opponent's moves are judged by the opponent's
+	/// heuristic.
+	fn search_moves(&self, board: &Board, team: Team, me: Team) -> Vec<usize> {
+		let possible_moves = if self.move_ordering {
+			board.possible_moves_ordered()
+		} else {
+			let (width, _) = board.dimensions();
+			(0..width).filter(|column| board.is_legal_move(*column)).collect()
+		};
+		let Some(beam_width) = self.beam_width else {
+			return possible_moves;
+		};
+
+		let mut move_values = possible_moves
+			.into_iter()
+			.map(|column| {
+				let mut test_board = *board;
+				test_board.put_tile(column, team).expect("Possible move was in fact impossible");
+
+				let value = match test_board.game_result_on_change(column) {
+					Some(GameResult::Draw) => 0.0,
+					Some(GameResult::Winner(winner)) => {
+						if winner == me {
+							f64::MAX
+						} else {
+							f64::MIN
 						}
-						None => {}
 					}
+					None => {
+						let (value, opponent_value) = self.evaluate(&test_board, me);
+						if team == me {
+							value
+						} else {
+							opponent_value
+						}
+					}
+				};
+				(column, value)
+			})
+			.collect::<Vec<_>>();
+		// Best moves for the team to move first, NaN values last. The sort is
+		// stable, so ties keep the search order.
+		move_values.sort_by(|(_, value_a), (_, value_b)| {
+			if team == me {
+				nan_worst_for_max(value_b, value_a)
+			} else {
+				nan_worst_for_min(value_a, value_b)
+			}
+		});
+		move_values.into_iter().take(beam_width).map(|(column, _)| column).collect()
+	}
 
-					self.min_value(&test_board, me, current_deepness + 1)
-				})
-				.max_by(|val_a, val_b| {
-					val_a.partial_cmp(val_b).expect("Heuristic value comparison failed")
-				})
-				.expect("No possible moves")
-		} else {
-			(self.heuristic)(board, me)
+	/// Value of a position that ended the game, for both heuristics.
+	fn terminal_value(result: GameResult, me: Team) -> (f64, f64) {
+		match result {
+			GameResult::Draw => (0.0, 0.0),
+			GameResult::Winner(team) if team == me => (f64::MAX, f64::MAX),
+			GameResult::Winner(_) => (f64::MIN, f64::MIN),
 		}
 	}
 
-	/// Other player's turn, minimize the heuristic value to take the other
-	/// player's best turn into account.
-	fn min_value(&self, board: &Board, me: Team, current_deepness: usize) -> f64 {
-		if current_deepness + 1 < self.deepness {
-			board
-				.possible_moves()
-				.into_iter()
-				.map(|column| {
-					let mut test_board = *board;
-					test_board
-						.put_tile(column, me.other())
-						.expect("Possible move was in fact impossible");
-
-					match test_board.game_result_on_change(column) {
-						Some(GameResult::Draw) => return 0.0,
-						Some(GameResult::Winner(team)) => {
-							return if team == me { f64::MAX } else { f64::MIN }
-						}
-						None => {}
-					}
+	/// Our turn, take the best value out of our turns. Returns our value and
+	/// the opponent's value of the chosen move.
+	///
+	/// With pruning, the search stops as soon as a move's value exceeds `beta`,
+	/// as the opponent would avoid this position then. The cutoffs are strict,
+	/// so values within `[alpha, beta]` are exact and ties are broken like in
+	/// the full search.
+	fn max_value(
+		&self,
+		limits: &SearchLimits,
+		board: &Board,
+		me: Team,
+		current_deepness: usize,
+		mut alpha: f64,
+		beta: f64,
+	) -> (f64, f64) {
+		self.visit(current_deepness);
+		if current_deepness + 1 >= limits.deepness || limits.expired() {
+			return self.evaluate(board, me);
+		}
+		let remaining = limits.deepness - current_deepness;
+		if let Some(values) = self.lookup_search(board, me, remaining, alpha, beta) {
+			return values;
+		}
+		let window = (alpha, beta);
 
-					self.max_value(&test_board, me, current_deepness + 1)
-				})
-				.min_by(|val_a, val_b| {
-					val_a.partial_cmp(val_b).expect("Heuristic value comparison failed")
-				})
-				.expect("No possible moves")
-		} else {
-			(self.heuristic)(board, me)
+		let mut best: Option<(f64, f64)> = None;
+		for column in self.search_moves(board, me, me) {
+			let mut test_board = *board;
+			test_board.put_tile(column, me).expect("Possible move was in fact impossible");
+
+			let values = match test_board.game_result_on_change(column) {
+				Some(result) => Self::terminal_value(result, me),
+				None => self.min_value(limits, &test_board, me, current_deepness + 1, alpha, beta),
+			};
+			// Earlier moves win ties.
+			match best {
+				Some((best, _)) if nan_worst_for_max(&best, &values.0).is_ge() => {}
+				_ => best = Some(values),
+			}
+
+			if self.prunes() {
+				// `f64::max` ignores NaN values.
+				alpha = alpha.max(values.0);
+				if alpha > beta {
+					break;
+				}
+			}
 		}
+		let best = best.expect("No possible moves");
+		self.store_search(limits, board, me, remaining, window, best);
+		best
+	}
+
+	/// Other player's turn, minimize the opponent's heuristic value to take
+	/// the other player's best turn into account. Returns our value and the
+	/// opponent's value of the chosen move.
+	///
+	/// With pruning, the search stops as soon as a move's value falls below
+	/// `alpha`, as we would avoid this position then.
+	fn min_value(
+		&self,
+		limits: &SearchLimits,
+		board: &Board,
+		me: Team,
+		current_deepness: usize,
+		alpha: f64,
+		mut beta: f64,
+	) -> (f64, f64) {
+		self.visit(current_deepness);
+		if current_deepness + 1 >= limits.deepness || limits.expired() {
+			return self.evaluate(board, me);
+		}
+		let remaining = limits.deepness - current_deepness;
+		if let Some(values) = self.lookup_search(board, me, remaining, alpha, beta) {
+			return values;
+		}
+		let window = (alpha, beta);
+
+		let mut best: Option<(f64, f64)> = None;
+		for column in self.search_moves(board, me.other(), me) {
+			let mut test_board = *board;
+			test_board.put_tile(column, me.other()).expect("Possible move was in fact impossible");
+
+			let values = match test_board.game_result_on_change(column) {
+				Some(result) => Self::terminal_value(result, me),
+				None => self.max_value(limits, &test_board, me, current_deepness + 1, alpha, beta),
+			};
+			// Earlier moves win ties, like `Iterator::min_by`.
+			match best {
+				Some((_, best)) if nan_worst_for_min(&values.1, &best).is_ge() => {}
+				_ => best = Some(values),
+			}
+
+			if self.prunes() {
+				// `f64::min` ignores NaN values.
+				beta = beta.min(values.0);
+				if alpha > beta {
+					break;
+				}
+			}
+		}
+		let best = best.expect("No possible moves");
+		self.store_search(limits, board, me, remaining, window, best);
+		best
 	}
 }
 
-impl<'a> Player for MinimaxPlayer<'a> {
-	fn make_move(&self, board: &Board, me: Team) -> usize {
-		board
-			.possible_moves()
+impl<'a> MinimaxPlayer<'a> {
+	/// Compute the minimax values of all possible moves within the limits. The
+	/// `first` move is searched first, if it is searched at all.
+	fn move_values(
+		&self,
+		limits: &SearchLimits,
+		board: &Board,
+		me: Team,
+		first: Option<usize>,
+	) -> Vec<(usize, f64)> {
+		let mut moves = self.search_moves(board, me, me);
+		if let Some(index) = moves.iter().position(|column| Some(*column) == first) {
+			moves[..=index].rotate_right(1);
+		}
+		moves
 			.into_iter()
 			.map(|column| {
 				let mut test_board = *board;
 				test_board.put_tile(column, me).expect("Possible move was in fact impossible");
 
-				match test_board.game_result_on_change(column) {
-					Some(GameResult::Draw) => return (column, 0.0),
-					Some(GameResult::Winner(team)) => {
-						return (column, if team == me { f64::MAX } else { f64::MIN })
-					}
-					None => {}
+				if let Some(result) = test_board.game_result_on_change(column) {
+					return (column, Self::terminal_value(result, me).0);
 				}
 
-				let value = self.min_value(&test_board, me, 1);
+				// Search every move with the full window to get exact values.
+				let (value, _) =
+					self.min_value(limits, &test_board, me, 1, f64::NEG_INFINITY, f64::INFINITY);
 				(column, value)
 			})
-			.max_by(|(_, value_a), (_, value_b)| {
-				value_a.partial_cmp(value_b).expect("Heuristic value comparison failed")
-			})
-			.expect("No possible move")
-			.0
+			.collect()
+	}
+
+	/// Get the best move and its value. The first of equally good moves in
+	/// search order wins, which is the most central one.
+	fn best_move(move_values: &[(usize, f64)]) -> Option<(usize, f64)> {
+		move_values.iter().copied().reduce(|best, next| {
+			if nan_worst_for_max(&next.1, &best.1).is_gt() {
+				next
+			} else {
+				best
+			}
+		})
+	}
+
+	/// Search the position, returning the values of all possible moves and the
+	/// deepness they were searched to. Without a time budget, this searches to
+	/// the configured deepness. Otherwise, this searches with iterative
+	/// deepening until the budget is used up, returning `None` if not even the
+	/// first iteration completed.
+	fn search(&self, board: &Board, me: Team) -> Option<(Vec<(usize, f64)>, usize)> {
+		let Some(budget) = self.budget else {
+			let limits = SearchLimits { deepness: self.deepness, deadline: None };
+			return Some((self.move_values(&limits, board, me, None), self.deepness));
+		};
+
+		let deadline = Instant::now() + budget;
+		// Searching deeper than the number of empty fields doesn't change anything.
+		let (width, height) = board.dimensions();
+		let max_deepness = self.deepness.min(width * height - board.filled());
+		let mut completed = None;
+		let mut first = None;
+		for deepness in 1..=max_deepness {
+			let limits = SearchLimits { deepness, deadline: Some(deadline) };
+			let move_values = self.move_values(&limits, board, me, first);
+			if limits.expired() {
+				break;
+			}
+			first = Self::best_move(&move_values).map(|(column, _)| column);
+			completed = Some((move_values, deepness));
+		}
+		completed
+	}
+}
+
+impl<'a> MinimaxPlayer<'a> {
+	/// Evaluate all searched moves, returning the columns with their minimax
+	/// values, sorted from best to worst. Equally good moves keep the search
+	/// order, so the first move is the one [`make_move`](Player::make_move)
+	/// plays without randomization. The list is empty if a timed search didn't
+	/// complete its first iteration.
+	pub fn evaluate_moves(&self, board: &Board, me: Team) -> Vec<(usize, f64)> {
+		self.nodes_visited.store(0, Ordering::Relaxed);
+		self.max_depth_reached.store(0, Ordering::Relaxed);
+		let start = Instant::now();
+		let search = self.search(board, me);
+		*self.last_search_stats.lock().expect("lock poisened") = SearchStats {
+			nodes_visited: self.nodes_visited.load(Ordering::Relaxed),
+			max_depth_reached: self.max_depth_reached.load(Ordering::Relaxed),
+			elapsed: start.elapsed(),
+			deepness: search.as_ref().map_or(0, |(_, deepness)| *deepness),
+		};
+
+		let Some((mut move_values, _)) = search else {
+			return Vec::new();
+		};
+		// Stable sort, NaN values last.
+		move_values.sort_by(|(_, value_a), (_, value_b)| nan_worst_for_max(value_b, value_a));
+		move_values
+	}
+}
+
+impl<'a> Player for MinimaxPlayer<'a> {
+	fn make_move(&self, board: &Board, me: Team) -> usize {
+		let move_values = self.evaluate_moves(board, me);
+		let Some(&(best_column, best_value)) = move_values.first() else {
+			return board.possible_moves_ordered().first().copied().expect("No possible move");
+		};
+
+		let Some((epsilon, rng)) = &self.randomization else {
+			return best_column;
+		};
+		// Sort the candidates to be independent of the move iteration order.
+		let mut candidates = move_values
+			.into_iter()
+			.filter(|(_, value)| *value >= best_value - epsilon)
+			.map(|(column, _)| column)
+			.collect::<Vec<_>>();
+		candidates.sort_unstable();
+		// Nothing compares within epsilon of a NaN best value, i.e. all values are
+		// NaN.
+		if candidates.is_empty() {
+			return best_column;
+		}
+		let mut rng = rng.lock().expect("lock poisened");
+		candidates[rng.gen_range(0..candidates.len())]
+	}
+
+	fn scored_moves(&self, board: &Board, me: Team) -> Option<Vec<(usize, f64)>> {
+		let mut move_values = self.evaluate_moves(board, me);
+		if move_values.is_empty() {
+			return None;
+		}
+		move_values.sort_unstable_by_key(|(column, _)| *column);
+		Some(move_values)
+	}
+
+	fn new_game(&self) {
+		if let Some(table) = &self.table {
+			table.lock().expect("lock poisened").clear();
+		}
+	}
+
+	fn name(&self) -> &str {
+		"minimax"
 	}
 }
 
@@ -124,7 +670,389 @@ impl<'a> Debug for MinimaxPlayer<'a> {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		f.debug_struct("MinimaxPlayer")
 			.field("deepness", &self.deepness)
+			.field("budget", &self.budget)
 			.field("heuristic", &"<fn>")
+			.field("opponent_heuristic", &self.opponent_heuristic.map(|_| "<fn>"))
+			.field("randomization", &self.randomization)
+			.field("beam_width", &self.beam_width)
+			.field("last_search_stats", &self.last_search_stats)
+			.field("transposition_table", &self.table.is_some())
+			.field("pruning", &self.pruning)
+			.field("move_ordering", &self.move_ordering)
 			.finish()
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use game::Game;
+
+	use super::*;
+
+	/// Heuristic preferring a tile at the bottom of column 3.
+	fn prefer_center(board: &Board, _me: Team) -> f64 {
+		if board.field()[3 * 6].is_some() {
+			1.0
+		} else {
+			0.9
+		}
+	}
+
+	/// Heuristic without ties, valuing tiles by their index in the field.
+	fn weighted_tiles(board: &Board, me: Team) -> f64 {
+		board
+			.field()
+			.iter()
+			.enumerate()
+			.map(|(i, tile)| match tile {
+				Some(team) if *team == me => (i + 1) as f64,
+				Some(_) => -0.5 * (i + 1) as f64,
+				None => 0.0,
+			})
+			.sum()
+	}
+
+	/// Sort move values by column to be able to compare them.
+	fn sorted(mut move_values: Vec<(usize, f64)>) -> Vec<(usize, f64)> {
+		move_values.sort_by_key(|(column, _)| *column);
+		move_values
+	}
+
+	/// Build the board by playing the given sequence of 0-indexed columns.
+	fn position(moves: &str) -> Board {
+		let mut board = Board::default();
+		for column in moves.chars() {
+			let column = column.to_digit(10).unwrap() as usize;
+			board.put_tile(column, board.whos_turn()).unwrap();
+		}
+		board
+	}
+
+	#[test]
+	fn full_beam_matches_minimax() {
+		let mut board = Board::default();
+		board.put_tile(3, Team::X).unwrap();
+		board.put_tile(2, Team::O).unwrap();
+		board.put_tile(3, Team::X).unwrap();
+
+		let minimax = MinimaxPlayer::new_1(4);
+		let beam = MinimaxPlayer::new_1(4).with_beam_width(7);
+		assert_eq!(
+			sorted(beam.scored_moves(&board, Team::O).unwrap()),
+			sorted(minimax.scored_moves(&board, Team::O).unwrap())
+		);
+	}
+
+	#[test]
+	fn beam_width_1_is_greedy() {
+		let greedy = MinimaxPlayer::new(1, &weighted_tiles);
+		let beam = MinimaxPlayer::new(4, &weighted_tiles).with_beam_width(1);
+		assert_eq!(beam.scored_moves(&Board::default(), Team::X).unwrap().len(), 1);
+		assert_eq!(
+			beam.make_move(&Board::default(), Team::X),
+			greedy.make_move(&Board::default(), Team::X)
+		);
+		assert_eq!(beam.make_move(&Board::default(), Team::X), 6);
+	}
+
+	#[test]
+	fn randomized_with_zero_epsilon_is_deterministic() {
+		let deterministic = MinimaxPlayer::new(1, &prefer_center);
+		for seed in 0..20 {
+			let randomized = MinimaxPlayer::new_randomized(1, &prefer_center, 0.0, seed);
+			assert_eq!(
+				randomized.make_move(&Board::default(), Team::X),
+				deterministic.make_move(&Board::default(), Team::X)
+			);
+		}
+	}
+
+	#[test]
+	fn randomized_with_epsilon_picks_worse_moves() {
+		let randomized = MinimaxPlayer::new_randomized(1, &prefer_center, 0.2, 42);
+		let picked_other = (0..50).any(|_| randomized.make_move(&Board::default(), Team::X) != 3);
+		assert!(picked_other);
+
+		// Same seed, same choices.
+		let a = MinimaxPlayer::new_randomized(1, &prefer_center, 0.2, 7);
+		let b = MinimaxPlayer::new_randomized(1, &prefer_center, 0.2, 7);
+		for _ in 0..10 {
+			assert_eq!(
+				a.make_move(&Board::default(), Team::X),
+				b.make_move(&Board::default(), Team::X)
+			);
+		}
+	}
+
+	#[test]
+	fn deeper_search_visits_more_nodes() {
+		let mut board = Board::default();
+		board.put_tile(3, Team::X).unwrap();
+		board.put_tile(3, Team::O).unwrap();
+
+		let shallow = MinimaxPlayer::new_1(2);
+		let deep = MinimaxPlayer::new_1(4);
+		assert_eq!(shallow.last_search_stats(), SearchStats::default());
+		shallow.make_move(&board, Team::X);
+		deep.make_move(&board, Team::X);
+
+		let (shallow, deep) = (shallow.last_search_stats(), deep.last_search_stats());
+		assert_eq!(shallow.nodes_visited, 7);
+		assert_eq!(shallow.max_depth_reached, 1);
+		assert!(deep.nodes_visited > shallow.nodes_visited);
+		assert_eq!(deep.max_depth_reached, 3);
+	}
+
+	#[test]
+	fn min_nodes_use_opponent_heuristic() {
+		/// Opponent heuristic, the opponent always plays the lowest free field
+		/// index, i.e. the leftmost column.
+		fn leftmost(board: &Board, me: Team) -> f64 {
+			board
+				.field()
+				.iter()
+				.enumerate()
+				.filter(|(_, tile)| **tile == Some(me.other()))
+				.map(|(i, _)| i as f64)
+				.sum()
+		}
+
+		let board = Board::default();
+		let exploiting = MinimaxPlayer::new(3, &weighted_tiles).with_opponent_heuristic(&leftmost);
+		// The opponent answers at (0, 0), or at (0, 1) if we took (0, 0).
+		let expected =
+			vec![(0, 0.0), (1, 6.5), (2, 12.5), (3, 18.5), (4, 24.5), (5, 30.5), (6, 36.5)];
+		assert_eq!(sorted(exploiting.scored_moves(&board, Team::X).unwrap()), expected);
+
+		// Plain minimax assumes the opponent answers in the last column.
+		let plain = MinimaxPlayer::new(3, &weighted_tiles);
+		assert_eq!(sorted(plain.scored_moves(&board, Team::X).unwrap())[1], (1, -11.5));
+		let same = MinimaxPlayer::new(3, &weighted_tiles).with_opponent_heuristic(&weighted_tiles);
+		assert_eq!(
+			sorted(same.scored_moves(&board, Team::X).unwrap()),
+			sorted(plain.scored_moves(&board, Team::X).unwrap())
+		);
+	}
+
+	#[test]
+	fn nan_values_are_never_chosen() {
+		/// Heuristic returning NaN once a tile is in the center column.
+		fn nan_center(board: &Board, me: Team) -> f64 {
+			if board.field()[3 * 6].is_some() {
+				f64::NAN
+			} else {
+				weighted_tiles(board, me)
+			}
+		}
+
+		let board = Board::default();
+		for deepness in 1..4 {
+			let player = MinimaxPlayer::new(deepness, &nan_center);
+			let column = player.make_move(&board, Team::X);
+			assert_ne!(column, 3, "deepness {deepness}");
+			assert!(board.is_legal_move(column));
+			let values = player.scored_moves(&board, Team::X).unwrap();
+			assert!(!values[column].1.is_nan(), "deepness {deepness}");
+		}
+
+		// Randomization doesn't crash if there is no valid value at all.
+		let nan = |_: &Board, _: Team| f64::NAN;
+		for deepness in 1..4 {
+			let player = MinimaxPlayer::new_randomized(deepness, &nan, 0.5, 1);
+			assert!(board.is_legal_move(player.make_move(&board, Team::X)));
+		}
+	}
+
+	#[test]
+	fn table_reuse_keeps_moves() {
+		let reused = MinimaxPlayer::new(4, &weighted_tiles).with_transposition_table();
+		reused.new_game();
+		let mut board = Board::default();
+		let mut team = Team::X;
+		while board.game_result().is_none() {
+			let fresh = MinimaxPlayer::new(4, &weighted_tiles).with_transposition_table();
+			let plain = MinimaxPlayer::new(4, &weighted_tiles);
+			let column = reused.make_move(&board, team);
+			assert_eq!(column, fresh.make_move(&board, team));
+			assert_eq!(column, plain.make_move(&board, team));
+
+			board.put_tile(column, team).unwrap();
+			team = team.other();
+		}
+
+		let table = reused.table.as_ref().unwrap();
+		assert!(!table.lock().unwrap().heuristic.is_empty());
+		assert!(!table.lock().unwrap().searches.is_empty());
+		reused.new_game();
+		assert!(table.lock().unwrap().heuristic.is_empty());
+		assert!(table.lock().unwrap().searches.is_empty());
+	}
+
+	#[test]
+	fn pruning_keeps_moves_and_visits_fewer_nodes() {
+		for moves in ["", "3324421055", "433522661340", "2345332410164"] {
+			let board = position(moves);
+			let me = board.whos_turn();
+
+			let pruned = MinimaxPlayer::new(4, &weighted_tiles);
+			let full = MinimaxPlayer::new(4, &weighted_tiles).without_pruning();
+			assert_eq!(pruned.make_move(&board, me), full.make_move(&board, me), "{moves}");
+			assert!(
+				pruned.last_search_stats().nodes_visited < full.last_search_stats().nodes_visited,
+				"{moves}"
+			);
+			assert_eq!(
+				sorted(pruned.scored_moves(&board, me).unwrap()),
+				sorted(full.scored_moves(&board, me).unwrap()),
+				"{moves}"
+			);
+
+			let pruned = MinimaxPlayer::new_1(4);
+			let full = MinimaxPlayer::new_1(4).without_pruning();
+			assert_eq!(
+				sorted(pruned.scored_moves(&board, me).unwrap()),
+				sorted(full.scored_moves(&board, me).unwrap()),
+				"{moves}"
+			);
+		}
+	}
+
+	#[test]
+	fn table_search_keeps_move_values() {
+		for moves in ["", "3324421055", "433522661340"] {
+			let board = position(moves);
+			let me = board.whos_turn();
+			for deepness in [3, 4] {
+				let cached = MinimaxPlayer::new_1(deepness).with_transposition_table();
+				let plain = MinimaxPlayer::new_1(deepness);
+				assert_eq!(
+					sorted(cached.scored_moves(&board, me).unwrap()),
+					sorted(plain.scored_moves(&board, me).unwrap()),
+					"{moves} at {deepness}"
+				);
+				let searches = cached.table.as_ref().unwrap().lock().unwrap().searches.len();
+				assert!(searches > 0, "{moves} at {deepness}");
+
+				// Without ties, so that the moves are unique.
+				let cached =
+					MinimaxPlayer::new(deepness, &weighted_tiles).with_transposition_table();
+				let plain = MinimaxPlayer::new(deepness, &weighted_tiles);
+				assert_eq!(cached.make_move(&board, me), plain.make_move(&board, me));
+			}
+		}
+	}
+
+	#[test]
+	fn timed_search_deepens_within_budget() {
+		/// Rank of the game result from X's view, higher is better.
+		fn rank(result: GameResult) -> usize {
+			match result {
+				GameResult::Winner(Team::X) => 2,
+				GameResult::Draw => 1,
+				GameResult::Winner(Team::O) => 0,
+			}
+		}
+
+		// X wins in column 0, the central fallback move doesn't.
+		let board = position("010106");
+		let opponent = MinimaxPlayer::new_1(3);
+		let mut results = Vec::new();
+		// The generous budget is never used up, the deepness limit ends the search, so
+		// the result doesn't depend on the machine's speed.
+		for budget in [Duration::ZERO, Duration::from_secs(3600)] {
+			let timed = MinimaxPlayer {
+				deepness: 3,
+				..MinimaxPlayer::new_timed(budget, &Board::heuristic_1)
+			};
+			let column = timed.make_move(&board, Team::X);
+			assert!(board.is_legal_move(column));
+			if budget.is_zero() {
+				assert_eq!(column, 3);
+				assert_eq!(timed.last_search_stats().deepness, 0);
+			} else {
+				assert_eq!(column, opponent.make_move(&board, Team::X));
+				assert_eq!(column, 0);
+				assert_eq!(timed.last_search_stats().deepness, 3);
+			}
+
+			let mut game =
+				Game::builder().player_x(&timed).player_o(&opponent).board(board).build();
+			results.push(rank(game.run_error_loss()));
+		}
+		assert!(results[1] >= results[0], "{results:?}");
+		assert_eq!(results[1], 2);
+	}
+
+	#[test]
+	fn center_ordering_visits_fewer_nodes() {
+		let mut rng = StdRng::seed_from_u64(1);
+		let (mut ordered_nodes, mut unordered_nodes) = (0, 0);
+		for _ in 0..10 {
+			let mut board = Board::default();
+			for _ in 0..rng.gen_range(0..12) {
+				let moves = board.moves_center_ordered();
+				let column = moves[rng.gen_range(0..moves.len())];
+				board.put_tile(column, board.whos_turn()).unwrap();
+				if board.game_result().is_some() {
+					board.pop_tile(column).unwrap();
+					break;
+				}
+			}
+			let me = board.whos_turn();
+
+			let ordered = MinimaxPlayer::new_1(4);
+			let unordered = MinimaxPlayer::new_1(4).without_move_ordering();
+			assert_eq!(
+				ordered.scored_moves(&board, me).unwrap(),
+				unordered.scored_moves(&board, me).unwrap()
+			);
+			ordered.make_move(&board, me);
+			unordered.make_move(&board, me);
+			ordered_nodes += ordered.last_search_stats().nodes_visited;
+			unordered_nodes += unordered.last_search_stats().nodes_visited;
+		}
+		// Not every position profits, but most do.
+		assert!(ordered_nodes < unordered_nodes, "{ordered_nodes} vs. {unordered_nodes}");
+
+		// Ties are broken towards the center, independent of the search.
+		let flat = |_: &Board, _: Team| 0.0;
+		for deepness in 1..4 {
+			assert_eq!(
+				MinimaxPlayer::new(deepness, &flat).make_move(&Board::default(), Team::X),
+				3
+			);
+		}
+		let player = MinimaxPlayer::new(1, &flat);
+		assert_eq!(player.search_moves(&Board::default(), Team::X, Team::X), [3, 2, 4, 1, 5, 0, 6]);
+		// Left of the center wins ties between equally central moves.
+		let board = position("333333");
+		assert_eq!(player.make_move(&board, Team::X), 2);
+		let sides = |board: &Board, me: Team| {
+			let own = |x| board.tile_at(x, 0) == Some(me);
+			if own(2) || own(4) {
+				1.0
+			} else {
+				0.0
+			}
+		};
+		assert_eq!(MinimaxPlayer::new(1, &sides).make_move(&Board::default(), Team::X), 2);
+	}
+
+	#[test]
+	fn evaluated_moves_are_sorted_best_first() {
+		for moves in ["", "3324421055", "433522661340", "010106"] {
+			let board = position(moves);
+			let me = board.whos_turn();
+			let player = MinimaxPlayer::new_1(4);
+
+			let evaluated = player.evaluate_moves(&board, me);
+			assert_eq!(evaluated.len(), board.possible_moves().len(), "{moves}");
+			assert!(evaluated.windows(2).all(|pair| pair[0].1 >= pair[1].1), "{moves}");
+			assert_eq!(player.make_move(&board, me), evaluated[0].0, "{moves}");
+
+			assert_eq!(sorted(evaluated), player.scored_moves(&board, me).unwrap(), "{moves}");
+		}
+	}
+}
@@ -1,23 +1,38 @@
 //! Player implementation using a convolutional neural network model to choose a
 //! column.
 
-use std::path::Path;
+use std::{
+	path::Path,
+	sync::atomic::{AtomicUsize, Ordering},
+};
 
 use burn::{
-	module::Module,
+	module::{ADModule, ConstantRecord, Module, ModuleMapper, ModuleVisitor},
 	nn::{
 		conv::{Conv2d, Conv2dConfig},
 		Linear, LinearConfig, GELU,
 	},
 	record::{FullPrecisionSettings, NamedMpkGzFileRecorder},
-	tensor::{activation::softmax, backend::Backend, ElementConversion, Tensor},
+	tensor::{
+		activation::softmax,
+		backend::{ADBackend, Backend},
+		ElementConversion, Tensor,
+	},
 };
 use game::{Board, Player, Team};
 
+use crate::{compare::nan_worst_for_max, InputEncoding};
+
 /// Convolutional neural network model to choose a connect four column. Model
 /// and player at once.
 #[derive(Debug, Module)]
 pub struct AiPolicyPlayer<B: Backend> {
+	/// Number of input planes, see [`InputEncoding`].
+	input_planes: usize,
+	/// Width of the boards the model works on.
+	width: usize,
+	/// Height of the boards the model works on.
+	height: usize,
 	/// Conv layer 1.
 	conv1: Conv2d<B>,
 	/// Linear layer 1.
@@ -28,18 +43,71 @@ pub struct AiPolicyPlayer<B: Backend> {
 	linear3: Linear<B>,
 	/// Activation.
 	activation: GELU,
+	/// Number of moves, where the model's most probable column was full.
+	illegal_argmax: IllegalArgmaxCounter,
+}
+
+/// Counter of moves, where the model's most probable column was full and a
+/// legal column had to be played instead. Useful for analyzing training runs.
+/// Not a model parameter, so it is not part of the saved record.
+#[derive(Debug, Default)]
+struct IllegalArgmaxCounter(AtomicUsize);
+
+impl Clone for IllegalArgmaxCounter {
+	fn clone(&self) -> Self {
+		Self(AtomicUsize::new(self.0.load(Ordering::Relaxed)))
+	}
+}
+
+impl<B: Backend> Module<B> for IllegalArgmaxCounter {
+	type Record = ConstantRecord;
+
+	fn visit<V: ModuleVisitor<B>>(&self, _visitor: &mut V) {}
+
+	fn map<M: ModuleMapper<B>>(self, _mapper: &mut M) -> Self {
+		self
+	}
+
+	fn load_record(self, _record: Self::Record) -> Self {
+		self
+	}
+
+	fn into_record(self) -> Self::Record {
+		ConstantRecord::new()
+	}
+}
+
+impl<B: ADBackend> ADModule<B> for IllegalArgmaxCounter {
+	type InnerModule = Self;
+
+	fn valid(&self) -> Self::InnerModule {
+		self.clone()
+	}
 }
 
 impl<B: Backend> AiPolicyPlayer<B> {
-	/// Create new fresh random model.
+	/// Create new fresh random model, using the single plane input encoding.
 	#[must_use]
 	pub fn init() -> Self {
+		Self::init_with_encoding(InputEncoding::SinglePlane)
+	}
+
+	/// Create new fresh random model using the given input encoding.
+	#[must_use]
+	pub fn init_with_encoding(encoding: InputEncoding) -> Self {
+		let (width, height) = Board::default().dimensions();
+		assert!(width >= 4 && height >= 4, "Board too small for the 4x4 kernel");
 		Self {
-			conv1: Conv2dConfig::new([1, 16], [4, 4]).init(),
-			linear1: LinearConfig::new(16 * 3 * 4, 100).init(), // 4x4 kernel makes 6x7 to 3x4.
+			input_planes: encoding.planes(),
+			width,
+			height,
+			conv1: Conv2dConfig::new([encoding.planes(), 16], [4, 4]).init(),
+			// 4x4 kernel makes 6x7 to 3x4.
+			linear1: LinearConfig::new(16 * (height - 3) * (width - 3), 100).init(),
 			linear2: LinearConfig::new(100, 50).init(),
-			linear3: LinearConfig::new(50, 7).init(),
+			linear3: LinearConfig::new(50, width).init(),
 			activation: GELU::new(),
+			illegal_argmax: IllegalArgmaxCounter::default(),
 		}
 		.no_grad()
 	}
@@ -55,11 +123,16 @@ impl<B: Backend> AiPolicyPlayer<B> {
 		self.save_file(path.as_ref(), &NamedMpkGzFileRecorder::<FullPrecisionSettings>::new())
 	}
 
-	/// Run model prediction.
-	fn forward(&self, field: Tensor<B, 3>) -> Tensor<B, 2> {
-		let [batch, height, width] = field.dims();
-		let data = field.reshape([batch, 1, height, width]);
-		let data = self.conv1.forward(data);
+	/// Get the input encoding of the model.
+	#[must_use]
+	pub fn encoding(&self) -> InputEncoding {
+		InputEncoding::from_planes(self.input_planes)
+	}
+
+	/// Run model prediction. Takes the fields in shape [batch, planes, height,
+	/// width].
+	fn forward(&self, field: Tensor<B, 4>) -> Tensor<B, 2> {
+		let data = self.conv1.forward(field);
 		let data = self.activation.forward(data);
 		let [batch, channels, height, width] = data.dims();
 		let data = data.reshape([batch, channels * height * width]);
@@ -71,33 +144,127 @@ impl<B: Backend> AiPolicyPlayer<B> {
 		softmax(data, 1)
 	}
 
-	/// Convert the board to a workable tensor.
-	fn board_to_tensor(board: &Board, me: Team) -> Tensor<B, 2> {
-		let data: Vec<_> = board
-			.field()
-			.iter()
-			.map(|tile| match tile {
-				None => 0.0,
-				Some(team) if *team == me => 1.0,
-				_ => -1.0,
-			})
-			.collect();
-		Tensor::from_floats(data.as_slice()).reshape([7, 6]).transpose()
+	/// Convert the board to a workable tensor of shape [planes, height, width].
+	fn board_to_tensor(&self, board: &Board, me: Team) -> Tensor<B, 3> {
+		let data = self.encoding().encode(board, me);
+		let (width, height) = board.dimensions();
+		Tensor::from_floats(data.as_slice()).reshape([self.input_planes, width, height]).transpose()
 	}
 
-	/// Convert board to a field tensor and run the model prediction.
-	fn predict(&self, board: &Board, me: Team) -> usize {
-		assert_eq!(board.dimensions(), (7, 6));
-		let data = Self::board_to_tensor(board, me);
+	/// Make sure the board has the size the model was created for.
+	fn check_dimensions(&self, board: &Board) {
+		assert_eq!(
+			board.dimensions(),
+			(self.width, self.height),
+			"Board size does not match the model"
+		);
+	}
+
+	/// Convert board to a field tensor and run the model prediction. Returns
+	/// the probability of each column.
+	fn predict(&self, board: &Board, me: Team) -> Vec<f32> {
+		self.check_dimensions(board);
+		let data = self.board_to_tensor(board, me);
 
-		let classes = self.forward(data.reshape([1, 6, 7])).reshape([7]);
-		let select: u8 = classes.argmax(0).into_scalar().elem();
-		select as usize
+		let classes = self
+			.forward(data.reshape([1, self.input_planes, self.height, self.width]))
+			.reshape([self.width]);
+		classes.into_data().value.into_iter().map(ElementConversion::elem).collect()
+	}
+
+	/// Number of moves made by this player, where the most probable column was
+	/// full and the most probable legal column was played instead.
+	#[must_use]
+	pub fn illegal_argmax_count(&self) -> usize {
+		self.illegal_argmax.0.load(Ordering::Relaxed)
+	}
+}
+
+/// Select the column with the highest probability. If that column is full,
+/// the most probable legal column is selected instead and the fallback is
+/// counted in `fallbacks`. Only returns an illegal column if the board is full.
+fn select_column(board: &Board, probabilities: &[f32], fallbacks: &AtomicUsize) -> usize {
+	let best = |legal_only: bool| {
+		probabilities
+			.iter()
+			.enumerate()
+			.filter(|(column, _)| !legal_only || board.is_legal_move(*column))
+			.max_by(|(_, a), (_, b)| nan_worst_for_max(&f64::from(**a), &f64::from(**b)))
+			.map(|(column, _)| column)
+	};
+
+	let argmax = best(false).unwrap_or_default();
+	if board.is_legal_move(argmax) {
+		return argmax;
+	}
+	match best(true) {
+		Some(column) => {
+			fallbacks.fetch_add(1, Ordering::Relaxed);
+			column
+		}
+		None => argmax,
 	}
 }
 
 impl<B: Backend> Player for AiPolicyPlayer<B> {
 	fn make_move(&self, board: &Board, me: Team) -> usize {
-		self.predict(board, me)
+		select_column(board, &self.predict(board, me), &self.illegal_argmax.0)
+	}
+
+	fn name(&self) -> &str {
+		"policy-net"
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used, clippy::float_cmp)]
+
+	use super::*;
+	use crate::NdArrayBackend;
+
+	#[test]
+	fn board_tensor_layout() {
+		let model = AiPolicyPlayer::<NdArrayBackend>::init();
+		let mut board = Board::default();
+		board.put_tile(0, Team::X).unwrap();
+		board.put_tile(0, Team::O).unwrap();
+		board.put_tile(6, Team::X).unwrap();
+
+		let tensor = model.board_to_tensor(&board, Team::X);
+		assert_eq!(tensor.dims(), [1, 6, 7]);
+		let data = tensor.into_data().value;
+		for y in 0..6 {
+			for x in 0..7 {
+				let expected = match (y, x) {
+					(0, 0) | (0, 6) => 1.0,
+					(1, 0) => -1.0,
+					_ => 0.0,
+				};
+				assert_eq!(data[y * 7 + x], expected, "y {y}, x {x}");
+			}
+		}
+	}
+
+	#[test]
+	fn full_argmax_falls_back_to_legal_column() {
+		let mut board = Board::default();
+		for _ in 0..3 {
+			board.put_tile(0, Team::X).unwrap();
+			board.put_tile(0, Team::O).unwrap();
+		}
+		let probabilities = [0.7, 0.05, 0.05, 0.05, 0.1, 0.0, 0.05];
+
+		let fallbacks = AtomicUsize::new(0);
+		assert_eq!(select_column(&board, &probabilities, &fallbacks), 4);
+		assert_eq!(fallbacks.load(Ordering::Relaxed), 1);
+		assert_eq!(select_column(&Board::default(), &probabilities, &fallbacks), 0);
+		assert_eq!(fallbacks.load(Ordering::Relaxed), 1);
+
+		let model = AiPolicyPlayer::<NdArrayBackend>::init();
+		assert_eq!(model.illegal_argmax_count(), 0);
+		assert!(board.is_legal_move(model.make_move(&board, Team::X)));
+		assert!(model.illegal_argmax_count() <= 1);
+		assert_eq!(model.clone().illegal_argmax_count(), model.illegal_argmax_count());
 	}
 }
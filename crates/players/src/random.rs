@@ -1,16 +1,110 @@
 //! Random player implementation.
 
+use std::sync::Mutex;
+
 use game::{Board, Player, Team};
-use rand::{seq::IteratorRandom, thread_rng};
+use rand::{rngs::StdRng, seq::IteratorRandom, thread_rng, Rng, SeedableRng};
 
 /// Random player.
 #[derive(Debug)]
 pub struct RandomPlayer;
 
 impl Player for RandomPlayer {
+	/// Pick a uniformly random set bit of the possible moves mask, which avoids
+	/// allocating the set of possible moves.
+	fn make_move(&self, board: &Board, _me: Team) -> usize {
+		let mut mask = board.possible_moves_mask();
+		assert!(mask != 0, "No possible moves");
+		let skip = thread_rng().gen_range(0..mask.count_ones());
+		for _ in 0..skip {
+			// Clear the lowest set bit.
+			mask &= mask - 1;
+		}
+		mask.trailing_zeros() as usize
+	}
+
+	fn name(&self) -> &str {
+		"random"
+	}
+}
+
+/// Random player with a seeded random number generator, so that its moves are
+/// reproducible.
+#[derive(Debug)]
+pub struct SeededRandomPlayer {
+	/// Random number generator to pick the moves.
+	rng: Mutex<StdRng>,
+}
+
+impl SeededRandomPlayer {
+	/// Create a new random player from the seed.
+	#[must_use]
+	pub fn new(seed: u64) -> Self {
+		Self { rng: Mutex::new(StdRng::seed_from_u64(seed)) }
+	}
+}
+
+impl Player for SeededRandomPlayer {
 	fn make_move(&self, board: &Board, _me: Team) -> usize {
-		let mut rng = thread_rng();
-		let possible_moves = board.possible_moves();
-		*possible_moves.iter().choose(&mut rng).expect("No possible moves")
+		let mut rng = self.rng.lock().expect("lock poisened");
+		// Iterate the columns in order, as the order of the possible moves set is not
+		// deterministic.
+		let (width, _) = board.dimensions();
+		(0..width)
+			.filter(|column| board.is_legal_move(*column))
+			.choose(&mut *rng)
+			.expect("No possible moves")
+	}
+
+	fn name(&self) -> &str {
+		"random"
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use super::*;
+
+	#[test]
+	fn uniform_over_legal_moves() {
+		let mut board = Board::default();
+		for _ in 0..6 {
+			board.put_tile(2, Team::X).unwrap();
+		}
+
+		let mut counts = [0_usize; 7];
+		for _ in 0..6000 {
+			counts[RandomPlayer.make_move(&board, Team::X)] += 1;
+		}
+		assert_eq!(counts[2], 0);
+		for (column, count) in counts.into_iter().enumerate().filter(|(column, _)| *column != 2) {
+			assert!((800..1200).contains(&count), "column {column}: {count}");
+		}
+	}
+
+	#[test]
+	fn same_seed_same_moves() {
+		let a = SeededRandomPlayer::new(5);
+		let b = SeededRandomPlayer::new(5);
+		let mut board = Board::default();
+		for _ in 0..10 {
+			let column = a.make_move(&board, board.whos_turn());
+			assert_eq!(b.make_move(&board, board.whos_turn()), column);
+			if !matches!(board.play(column, board.whos_turn()), Ok(None)) {
+				break;
+			}
+		}
+	}
+
+	#[test]
+	fn names_forward_through_trait_objects() {
+		let players: Vec<Box<dyn Player>> =
+			vec![Box::new(RandomPlayer), Box::new(SeededRandomPlayer::new(0))];
+		for player in &players {
+			assert_eq!(player.name(), "random");
+			assert_eq!((&player).name(), "random");
+		}
 	}
 }
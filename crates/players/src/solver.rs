@@ -0,0 +1,324 @@
+//! Solver player implementation, searching the game tree for the
+//! game-theoretic result using negamax with alpha-beta pruning.
+
+use game::{Board, GameResult, Player, Team};
+
+/// Solver player, playing perfectly within its search horizon. Scores take the
+/// distance to the result into account, so that it prefers the fastest win and
+/// the slowest loss.
+///
+/// Solving a position is only fast for positions with few empty fields left.
+/// Use the maximum search depth to limit the search for earlier positions.
+#[derive(Debug, Clone, Copy)]
+pub struct SolverPlayer {
+	/// Maximum number of plies to search.
+	max_depth: usize,
+	/// Whether to resign in proven lost positions.
+	resign: bool,
+}
+
+/// Game-theoretic classification of a position for the side to move, see
+/// [`SolverPlayer::classify`]. Distances are given in plies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PositionClass {
+	/// The side to move wins with perfect play after the given number of plies.
+	WinningIn(u8),
+	/// The game ends in a draw with perfect play.
+	Drawn,
+	/// The side to move loses with perfect play after the given number of
+	/// plies.
+	LosingIn(u8),
+}
+
+impl Default for SolverPlayer {
+	/// Create a solver without search depth limit.
+	fn default() -> Self {
+		Self { max_depth: usize::MAX, resign: false }
+	}
+}
+
+impl SolverPlayer {
+	/// Create a new solver that searches at most `max_depth` plies deep.
+	/// Positions that are undecided at the horizon are scored as draws.
+	#[must_use]
+	pub fn new(max_depth: usize) -> Self {
+		Self { max_depth, resign: false }
+	}
+
+	/// Let the solver resign when it finds a forced loss within its search
+	/// depth.
+	#[must_use]
+	pub fn with_resignation(mut self) -> Self {
+		self.resign = true;
+		self
+	}
+
+	/// Score base for won positions. A win after `p` plies is scored
+	/// `score_base - p`, a loss `p - score_base`.
+	#[allow(clippy::cast_possible_wrap)] // The board isn't that big, there is no wraps.
+	fn score_base(board: &Board) -> i32 {
+		let (width, height) = board.dimensions();
+		(width * height + 1) as i32
+	}
+
+	/// Negamax search. Returns the score of the position from the view of
+	/// `team`, which is the side to move. `depth` is the number of plies
+	/// already played from the root position.
+	#[allow(clippy::cast_possible_wrap)] // The board isn't that big, there is no wraps.
+	fn negamax(
+		&self,
+		board: &Board,
+		team: Team,
+		depth: usize,
+		mut alpha: i32,
+		mut beta: i32,
+	) -> i32 {
+		if depth >= self.max_depth {
+			return 0;
+		}
+
+		let score_base = Self::score_base(board);
+		// Central columns are usually the better moves and thus cause more
+		// cut-offs.
+		let moves = board.moves_center_ordered();
+
+		// Check for immediate results first, a win can't be any faster.
+		let mut children = Vec::with_capacity(moves.len());
+		for column in moves {
+			let mut test_board = *board;
+			test_board.put_tile(column, team).expect("Possible move was in fact impossible");
+			match test_board.game_result_on_change(column) {
+				Some(GameResult::Winner(_)) => return score_base - (depth + 1) as i32,
+				Some(GameResult::Draw) => return 0,
+				None => children.push(test_board),
+			}
+		}
+
+		// The opponent can't win before the ply after next.
+		let max_score = score_base - (depth + 2) as i32;
+		if beta > max_score {
+			beta = max_score;
+			if alpha >= beta {
+				return beta;
+			}
+		}
+
+		for child in children {
+			let score = -self.negamax(&child, team.other(), depth + 1, -beta, -alpha);
+			if score >= beta {
+				return score;
+			}
+			if score > alpha {
+				alpha = score;
+			}
+		}
+		alpha
+	}
+
+	/// Compute the scores of all possible moves of `me`. Returns the columns
+	/// with their scores, ordered center first.
+	fn move_scores(&self, board: &Board, me: Team) -> Vec<(usize, i32)> {
+		let score_base = Self::score_base(board);
+		board
+			.moves_center_ordered()
+			.into_iter()
+			.map(|column| {
+				let mut test_board = *board;
+				test_board.put_tile(column, me).expect("Possible move was in fact impossible");
+				let score = match test_board.game_result_on_change(column) {
+					Some(GameResult::Winner(_)) => score_base - 1,
+					Some(GameResult::Draw) => 0,
+					None => -self.negamax(&test_board, me.other(), 1, -score_base, score_base),
+				};
+				(column, score)
+			})
+			.collect()
+	}
+
+	/// Compute the result of the game under perfect play with `me` to move,
+	/// and the number of plies it takes to reach it. Returns `None` if the game
+	/// is already over or the result could not be determined within the
+	/// search depth.
+	#[must_use]
+	pub fn distance_to_result(&self, board: &Board, me: Team) -> Option<(GameResult, usize)> {
+		if board.game_result().is_some() {
+			return None;
+		}
+
+		let score_base = Self::score_base(board);
+		let score = self.negamax(board, me, 0, -score_base, score_base);
+		let (width, height) = board.dimensions();
+		let empty = width * height - board.filled();
+		if score > 0 {
+			Some((GameResult::Winner(me), (score_base - score) as usize))
+		} else if score < 0 {
+			Some((GameResult::Winner(me.other()), (score_base + score) as usize))
+		} else if self.max_depth >= empty {
+			Some((GameResult::Draw, empty))
+		} else {
+			None
+		}
+	}
+
+	/// Classify the position as winning, drawn or losing for `to_move` under
+	/// perfect play, see [`SolverPlayer::distance_to_result`]. Like in the
+	/// search, positions that are undecided within the search depth are
+	/// classified as drawn. Finished games are won or lost in 0 plies or drawn.
+	///
+	/// Without depth limit, the classification is exact, but only positions
+	/// with few empty fields can be solved in reasonable time. The empty
+	/// board, a first player win, is out of reach.
+	#[must_use]
+	pub fn classify(&self, board: &Board, to_move: Team) -> PositionClass {
+		let result = match board.game_result() {
+			Some(result) => Some((result, 0)),
+			None => self.distance_to_result(board, to_move),
+		};
+		let Some((result, plies)) = result else {
+			return PositionClass::Drawn;
+		};
+		let plies = u8::try_from(plies).unwrap_or(u8::MAX);
+		match result {
+			GameResult::Draw => PositionClass::Drawn,
+			GameResult::Winner(winner) if winner == to_move => PositionClass::WinningIn(plies),
+			GameResult::Winner(_) => PositionClass::LosingIn(plies),
+		}
+	}
+}
+
+impl Player for SolverPlayer {
+	fn make_move(&self, board: &Board, me: Team) -> usize {
+		// Reverse, so that the most central of equally good moves is picked.
+		self.move_scores(board, me)
+			.into_iter()
+			.rev()
+			.max_by_key(|(_, score)| *score)
+			.expect("No possible moves")
+			.0
+	}
+
+	fn scored_moves(&self, board: &Board, me: Team) -> Option<Vec<(usize, f64)>> {
+		let mut scores = self
+			.move_scores(board, me)
+			.into_iter()
+			.map(|(column, score)| (column, f64::from(score)))
+			.collect::<Vec<_>>();
+		scores.sort_unstable_by_key(|(column, _)| *column);
+		Some(scores)
+	}
+
+	fn should_resign(&self, board: &Board, me: Team) -> bool {
+		self.resign
+			&& matches!(
+				self.distance_to_result(board, me),
+				Some((GameResult::Winner(winner), _)) if winner != me
+			)
+	}
+
+	fn name(&self) -> &str {
+		"solver"
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use super::*;
+
+	#[test]
+	fn one_move_win_has_distance_1() {
+		let mut board = Board::default();
+		board.put_tile(0, Team::X).unwrap();
+		board.put_tile(0, Team::O).unwrap();
+		board.put_tile(1, Team::X).unwrap();
+		board.put_tile(1, Team::O).unwrap();
+		board.put_tile(2, Team::X).unwrap();
+		board.put_tile(2, Team::O).unwrap();
+
+		let solver = SolverPlayer::new(4);
+		assert_eq!(
+			solver.distance_to_result(&board, Team::X),
+			Some((GameResult::Winner(Team::X), 1))
+		);
+		assert_eq!(solver.make_move(&board, Team::X), 3);
+	}
+
+	#[test]
+	fn prefers_fastest_win() {
+		// X can win right away in column 3, or set up a slower win.
+		let mut board = Board::default();
+		board.put_tile(3, Team::X).unwrap();
+		board.put_tile(3, Team::X).unwrap();
+		board.put_tile(3, Team::X).unwrap();
+		board.put_tile(0, Team::O).unwrap();
+		board.put_tile(6, Team::O).unwrap();
+		board.put_tile(6, Team::O).unwrap();
+
+		let solver = SolverPlayer::new(5);
+		assert_eq!(solver.make_move(&board, Team::X), 3);
+	}
+
+	#[test]
+	fn resigns_forced_loss() {
+		// X has an open three in the bottom row, O can't block both ends.
+		let mut board = Board::default();
+		board.put_tile(1, Team::X).unwrap();
+		board.put_tile(1, Team::O).unwrap();
+		board.put_tile(2, Team::X).unwrap();
+		board.put_tile(2, Team::O).unwrap();
+		board.put_tile(3, Team::X).unwrap();
+
+		let solver = SolverPlayer::new(4);
+		assert!(!solver.should_resign(&board, Team::O));
+		assert!(solver.with_resignation().should_resign(&board, Team::O));
+		assert!(!solver.with_resignation().should_resign(&board, Team::X));
+	}
+
+	#[test]
+	fn classify_positions() {
+		let solver = SolverPlayer::new(4);
+		let mut board = Board::default();
+		for column in [0, 0, 1, 1, 2, 2] {
+			board.put_tile(column, board.whos_turn()).unwrap();
+		}
+		assert_eq!(solver.classify(&board, Team::X), PositionClass::WinningIn(1));
+
+		// X has an open three in the bottom row, O can only block one end.
+		let mut board = Board::default();
+		for column in [1, 1, 2, 2, 3] {
+			board.put_tile(column, board.whos_turn()).unwrap();
+		}
+		assert_eq!(solver.classify(&board, Team::O), PositionClass::LosingIn(2));
+
+		// Only one move is left, which draws.
+		let mut board = Board::default();
+		for column in [
+			4, 3, 6, 0, 1, 4, 5, 5, 1, 1, 5, 0, 1, 6, 0, 1, 5, 5, 1, 0, 4, 6, 3, 2, 6, 6, 0, 4, 6,
+			5, 2, 0, 4, 2, 4, 2, 2, 2, 3, 3, 3,
+		] {
+			board.put_tile(column, board.whos_turn()).unwrap();
+		}
+		assert_eq!(solver.classify(&board, Team::O), PositionClass::Drawn);
+		board.put_tile(3, Team::O).unwrap();
+		assert_eq!(solver.classify(&board, Team::X), PositionClass::Drawn);
+
+		// The first player sets up an open three in the bottom row and wins on
+		// the ply after next.
+		let mut board = Board::default();
+		for column in [1, 1, 2, 2] {
+			board.put_tile(column, board.whos_turn()).unwrap();
+		}
+		assert_eq!(solver.classify(&board, Team::X), PositionClass::WinningIn(3));
+		assert_eq!(SolverPlayer::new(2).classify(&board, Team::X), PositionClass::Drawn);
+		board.put_tile(3, Team::X).unwrap();
+		board.put_tile(0, Team::O).unwrap();
+		board.put_tile(4, Team::X).unwrap();
+		assert_eq!(solver.classify(&board, Team::O), PositionClass::LosingIn(0));
+		assert_eq!(solver.classify(&board, Team::X), PositionClass::WinningIn(0));
+
+		// The empty board is a first player win, but solving it takes far too long
+		// for a test. Within the search depth, it is undecided.
+		assert_eq!(solver.classify(&Board::default(), Team::X), PositionClass::Drawn);
+	}
+}
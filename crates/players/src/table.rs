@@ -0,0 +1,214 @@
+//! Move table and a player looking up moves in it.
+
+use std::{
+	collections::HashMap,
+	fs::File,
+	io::{BufRead, BufReader, BufWriter, Write},
+	path::Path,
+};
+
+use game::{Board, Player, Team};
+
+/// Table mapping positions to moves, e.g. optimal moves from a solver or book
+/// moves of an opening book. Positions are stored by their canonical key, so
+/// that mirrored positions share an entry.
+///
+/// The file format is plain text with one entry per line, consisting of the
+/// canonical key and the column for the canonical orientation, separated by
+/// whitespace. Empty lines and lines starting with `#` are ignored.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MoveTable {
+	/// Moves by canonical board key.
+	moves: HashMap<u64, usize>,
+}
+
+impl MoveTable {
+	/// Create a new empty table.
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Number of positions in the table.
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.moves.len()
+	}
+
+	/// Whether the table is empty.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.moves.is_empty()
+	}
+
+	/// Whether the board is in its canonical orientation.
+	fn is_canonical(board: &Board) -> bool {
+		board.key() == board.canonical_key()
+	}
+
+	/// Convert a column between the board's orientation and the canonical
+	/// orientation. The conversion is its own inverse.
+	fn orient_column(board: &Board, column: usize) -> usize {
+		if Self::is_canonical(board) {
+			column
+		} else {
+			let (width, _) = board.dimensions();
+			width - 1 - column
+		}
+	}
+
+	/// Insert the move for the given position.
+	pub fn insert(&mut self, board: &Board, column: usize) {
+		self.moves.insert(board.canonical_key(), Self::orient_column(board, column));
+	}
+
+	/// Get the move for the given position, if it is in the table.
+	#[must_use]
+	pub fn get(&self, board: &Board) -> Option<usize> {
+		self.moves.get(&board.canonical_key()).map(|column| Self::orient_column(board, *column))
+	}
+
+	/// Read a table in the text format.
+	pub fn from_reader(reader: impl BufRead) -> std::io::Result<Self> {
+		let invalid = |line: &str| {
+			std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				format!("Invalid move table entry: {line}"),
+			)
+		};
+
+		let mut moves = HashMap::new();
+		for line in reader.lines() {
+			let line = line?;
+			let trimmed = line.trim();
+			if trimmed.is_empty() || trimmed.starts_with('#') {
+				continue;
+			}
+
+			let mut parts = trimmed.split_whitespace();
+			let (Some(key), Some(column), None) = (parts.next(), parts.next(), parts.next()) else {
+				return Err(invalid(trimmed));
+			};
+			let key = key.parse().map_err(|_| invalid(trimmed))?;
+			let column = column.parse().map_err(|_| invalid(trimmed))?;
+			moves.insert(key, column);
+		}
+		Ok(Self { moves })
+	}
+
+	/// Write the table in the text format. Entries are sorted by key.
+	pub fn write_to(&self, mut writer: impl Write) -> std::io::Result<()> {
+		let mut entries = self.moves.iter().collect::<Vec<_>>();
+		entries.sort_unstable();
+		for (key, column) in entries {
+			writeln!(writer, "{key} {column}")?;
+		}
+		writer.flush()
+	}
+
+	/// Load a table from a file.
+	pub fn load(path: impl AsRef<Path>) -> std::io::Result<Self> {
+		Self::from_reader(BufReader::new(File::open(path)?))
+	}
+
+	/// Save the table to a file.
+	pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+		self.write_to(BufWriter::new(File::create(path)?))
+	}
+}
+
+/// Player playing the moves from a [`MoveTable`] for known positions and
+/// delegating to a fallback player otherwise.
+#[derive(Debug, Clone)]
+pub struct TableLookupPlayer<P> {
+	/// Table of known positions.
+	table: MoveTable,
+	/// Player for positions not in the table.
+	fallback: P,
+}
+
+impl<P> TableLookupPlayer<P> {
+	/// Create a new table lookup player.
+	#[must_use]
+	pub fn new(table: MoveTable, fallback: P) -> Self {
+		Self { table, fallback }
+	}
+
+	/// Get the move table.
+	#[must_use]
+	pub fn table(&self) -> &MoveTable {
+		&self.table
+	}
+}
+
+impl<P: Player> Player for TableLookupPlayer<P> {
+	fn make_move(&self, board: &Board, me: Team) -> usize {
+		match self.table.get(board) {
+			Some(column) if board.is_legal_move(column) => column,
+			_ => self.fallback.make_move(board, me),
+		}
+	}
+
+	fn should_resign(&self, board: &Board, me: Team) -> bool {
+		let known = matches!(self.table.get(board), Some(column) if board.is_legal_move(column));
+		!known && self.fallback.should_resign(board, me)
+	}
+
+	fn scored_moves(&self, board: &Board, me: Team) -> Option<Vec<(usize, f64)>> {
+		match self.table.get(board) {
+			Some(column) if board.is_legal_move(column) => None,
+			_ => self.fallback.scored_moves(board, me),
+		}
+	}
+
+	fn new_game(&self) {
+		self.fallback.new_game();
+	}
+
+	fn name(&self) -> &str {
+		"table"
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use super::*;
+
+	/// Player always choosing the same column.
+	#[derive(Debug)]
+	struct ColumnPlayer(usize);
+
+	impl Player for ColumnPlayer {
+		fn make_move(&self, _board: &Board, _me: Team) -> usize {
+			self.0
+		}
+	}
+
+	#[test]
+	fn uses_table_and_fallback() {
+		let mut known = Board::default();
+		known.put_tile(1, Team::X).unwrap();
+		let mut table = MoveTable::new();
+		table.insert(&known, 2);
+
+		let mut text = Vec::new();
+		table.write_to(&mut text).unwrap();
+		let table = MoveTable::from_reader(text.as_slice()).unwrap();
+		assert_eq!(table.len(), 1);
+
+		let player = TableLookupPlayer::new(table, ColumnPlayer(6));
+		assert_eq!(player.make_move(&known, Team::O), 2);
+		assert_eq!(player.make_move(&known.mirrored(), Team::O), 4);
+		assert_eq!(player.make_move(&Board::default(), Team::X), 6);
+	}
+
+	#[test]
+	fn rejects_invalid_entries() {
+		let table = MoveTable::from_reader("# comment\n\n42 3\n".as_bytes()).unwrap();
+		assert_eq!(table.len(), 1);
+		assert!(MoveTable::from_reader("42\n".as_bytes()).is_err());
+		assert!(MoveTable::from_reader("42 x\n".as_bytes()).is_err());
+	}
+}
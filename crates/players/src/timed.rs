@@ -0,0 +1,120 @@
+//! Player wrapper enforcing a time budget per move.
+
+use std::{
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		mpsc, Arc,
+	},
+	thread,
+	time::Duration,
+};
+
+use game::{Board, Player, Team};
+
+/// Player wrapper enforcing a hard time budget per move. The inner player's
+/// move is computed on a worker thread. If it doesn't finish within the
+/// timeout, the most central legal column is played instead.
+///
+/// [`Player::make_move`] can't be cancelled, so a timed out worker thread
+/// keeps running until the inner player returns, the wrapper just stops
+/// waiting for it. Slow players can therefore still occupy CPU time during
+/// the following moves.
+#[derive(Debug)]
+pub struct TimedPlayer<P> {
+	/// Player making the moves.
+	inner: Arc<P>,
+	/// Time budget per move.
+	timeout: Duration,
+	/// Number of moves that timed out.
+	timeouts: AtomicUsize,
+}
+
+impl<P> TimedPlayer<P> {
+	/// Wrap the player to limit its moves to the timeout.
+	#[must_use]
+	pub fn new(inner: P, timeout: Duration) -> Self {
+		Self { inner: Arc::new(inner), timeout, timeouts: AtomicUsize::new(0) }
+	}
+
+	/// Get the inner player.
+	#[must_use]
+	pub fn inner(&self) -> &P {
+		&self.inner
+	}
+
+	/// Get the time budget per move.
+	#[must_use]
+	pub fn timeout(&self) -> Duration {
+		self.timeout
+	}
+
+	/// Number of moves that timed out and were replaced by the fallback move.
+	#[must_use]
+	pub fn timeouts(&self) -> usize {
+		self.timeouts.load(Ordering::Relaxed)
+	}
+}
+
+impl<P: Player + Send + Sync + 'static> Player for TimedPlayer<P> {
+	fn make_move(&self, board: &Board, me: Team) -> usize {
+		let (sender, receiver) = mpsc::channel();
+		let inner = Arc::clone(&self.inner);
+		let board = *board;
+		thread::spawn(move || {
+			// The receiver is gone if the move timed out, nobody is interested then.
+			_ = sender.send(inner.make_move(&board, me));
+		});
+
+		receiver.recv_timeout(self.timeout).unwrap_or_else(|_| {
+			self.timeouts.fetch_add(1, Ordering::Relaxed);
+			board.moves_center_ordered().first().copied().expect("No possible moves")
+		})
+	}
+
+	fn should_resign(&self, board: &Board, me: Team) -> bool {
+		self.inner.should_resign(board, me)
+	}
+
+	/// Not limited by the timeout.
+	fn scored_moves(&self, board: &Board, me: Team) -> Option<Vec<(usize, f64)>> {
+		self.inner.scored_moves(board, me)
+	}
+
+	fn new_game(&self) {
+		self.inner.new_game();
+	}
+
+	fn name(&self) -> &str {
+		self.inner.name()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use super::*;
+
+	/// Player taking its time to play the first column.
+	#[derive(Debug)]
+	struct SlowPlayer(Duration);
+
+	impl Player for SlowPlayer {
+		fn make_move(&self, _board: &Board, _me: Team) -> usize {
+			thread::sleep(self.0);
+			0
+		}
+	}
+
+	#[test]
+	fn falls_back_on_timeout() {
+		let board = Board::default();
+		let fast = TimedPlayer::new(SlowPlayer(Duration::ZERO), Duration::from_secs(10));
+		assert_eq!(fast.make_move(&board, Team::X), 0);
+		assert_eq!(fast.timeouts(), 0);
+
+		let slow = TimedPlayer::new(SlowPlayer(Duration::from_secs(2)), Duration::from_millis(20));
+		assert_eq!(slow.make_move(&board, Team::X), 3);
+		assert_eq!(slow.timeouts(), 1);
+	}
+}
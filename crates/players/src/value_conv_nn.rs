@@ -1,20 +1,41 @@
 //! Player implementation using a convolutional neural network model as value
 //! heuristic.
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use burn::{
+	config::Config,
 	module::Module,
 	nn::{
 		conv::{Conv2d, Conv2dConfig},
-		Linear, LinearConfig,
+		Linear, LinearConfig, PaddingConfig2d,
 	},
-	record::{FullPrecisionSettings, NamedMpkGzFileRecorder},
+	record::{FullPrecisionSettings, NamedMpkGzFileRecorder, RecorderError},
 	tensor::{activation::tanh, backend::Backend, ElementConversion, Tensor},
 };
-use game::{Board, Player, Team};
+use game::{Board, GameResult, Player, Team};
 
-use crate::MinimaxPlayer;
+use crate::{
+	compare::{nan_worst_for_max, nan_worst_for_min},
+	InputEncoding,
+};
+
+/// Architecture configuration of the [`AiValuePlayer`] network. The default is
+/// the original architecture with a single conv layer.
+#[derive(Config, Debug)]
+pub struct ValueNetConfig {
+	/// Number of conv channels.
+	#[config(default = 16)]
+	pub channels: usize,
+	/// Total number of conv layers. Layers after the first one keep the
+	/// spatial size.
+	#[config(default = 1)]
+	pub conv_layers: usize,
+	/// Whether to add residual skip connections around the layers after the
+	/// first one.
+	#[config(default = false)]
+	pub residual: bool,
+}
 
 /// Convolutional neural network model to evaluate board positions. Model
 /// and player at once.
@@ -22,47 +43,167 @@ use crate::MinimaxPlayer;
 pub struct AiValuePlayer<B: Backend> {
 	/// Minimax deepness level.
 	deepness: usize,
+	/// Number of input planes, see [`InputEncoding`].
+	input_planes: usize,
+	/// Width of the boards the model works on.
+	width: usize,
+	/// Height of the boards the model works on.
+	height: usize,
 	/// Conv layer 1.
 	conv1: Conv2d<B>,
+	/// Further conv layers after the first one. Optional, so that models
+	/// saved before deeper networks were supported still load.
+	hidden_convs: Option<Vec<Conv2d<B>>>,
+	/// Whether the further conv layers have residual skip connections.
+	residual: bool,
+	/// Number of conv channels.
+	channels: usize,
 	/// Linear layer 1.
 	linear1: Linear<B>,
 	/// Linear layer 2.
 	linear2: Linear<B>,
 	/// Linear layer 3.
 	linear3: Linear<B>,
+	/// Scale of the model output, which is in the range `[-output_scale,
+	/// output_scale]`. Applied in double precision, so that large scales
+	/// don't overflow.
+	output_scale: f64,
 }
 
 impl<B: Backend> AiValuePlayer<B> {
-	/// Create new fresh random model.
+	/// Create new fresh random model, using the single plane input encoding.
 	#[must_use]
 	pub fn init(deepness: usize) -> Self {
+		Self::init_with_encoding(deepness, InputEncoding::SinglePlane)
+	}
+
+	/// Create new fresh random model using the given input encoding.
+	#[must_use]
+	pub fn init_with_encoding(deepness: usize, encoding: InputEncoding) -> Self {
+		Self::init_with_config(deepness, encoding, &ValueNetConfig::new())
+	}
+
+	/// Create new fresh random model using the given input encoding and
+	/// network architecture.
+	#[must_use]
+	pub fn init_with_config(
+		deepness: usize,
+		encoding: InputEncoding,
+		config: &ValueNetConfig,
+	) -> Self {
+		let channels = config.channels;
+		let (width, height) = Board::default().dimensions();
+		assert!(width >= 4 && height >= 4, "Board too small for the 4x4 kernel");
+		let hidden_convs = (1..config.conv_layers)
+			.map(|_| {
+				Conv2dConfig::new([channels, channels], [3, 3])
+					.with_padding(PaddingConfig2d::Same)
+					.init()
+			})
+			.collect();
 		Self {
 			deepness,
-			conv1: Conv2dConfig::new([1, 16], [4, 4]).init(),
-			linear1: LinearConfig::new(16 * 3 * 4, 100).init(), // 4x4 kernel makes 6x7 to 3x4.
+			input_planes: encoding.planes(),
+			width,
+			height,
+			conv1: Conv2dConfig::new([encoding.planes(), channels], [4, 4]).init(),
+			hidden_convs: Some(hidden_convs),
+			residual: config.residual,
+			channels,
+			// 4x4 kernel makes 6x7 to 3x4, the further layers keep the size.
+			linear1: LinearConfig::new(channels * (height - 3) * (width - 3), 100).init(),
 			linear2: LinearConfig::new(100, 50).init(),
 			linear3: LinearConfig::new(50, 1).init(),
+			output_scale: 1.0,
 		}
 		.no_grad()
 	}
 
-	/// Load the module from a file.
-	pub fn load(self, path: impl AsRef<Path>) -> Result<Self, burn::record::RecorderError> {
-		self.load_file(path.as_ref(), &NamedMpkGzFileRecorder::<FullPrecisionSettings>::new())
+	/// Scale the model output to the range `[-output_scale, output_scale]`
+	/// instead of `[-1, 1]`, e.g. to match the scale of another heuristic.
+	///
+	/// Won and lost positions in the search are valued `f64::MAX` and
+	/// `f64::MIN` respectively, so they rank above and below any evaluation
+	/// regardless of the scale, as long as the scale is finite. Draws are
+	/// valued 0, like an evaluation of an even position.
+	#[must_use]
+	pub fn with_output_scale(mut self, output_scale: f64) -> Self {
+		assert!(output_scale.is_finite() && output_scale > 0.0, "Invalid output scale");
+		self.output_scale = output_scale;
+		self
+	}
+
+	/// Get the architecture configuration of the model.
+	#[must_use]
+	pub fn config(&self) -> ValueNetConfig {
+		ValueNetConfig::new()
+			.with_channels(self.channels)
+			.with_conv_layers(1 + self.hidden_convs.as_ref().map_or(0, Vec::len))
+			.with_residual(self.residual)
+	}
+
+	/// Path of the architecture configuration saved next to the model file.
+	fn config_path(path: &Path) -> PathBuf {
+		let mut config_path = path.as_os_str().to_owned();
+		config_path.push(".config.json");
+		config_path.into()
+	}
+
+	/// Load the module from a file. If the architecture configuration was saved
+	/// next to it, the model is rebuilt with that architecture first, keeping
+	/// the deepness, input encoding and output scale. Otherwise, the model
+	/// needs to have the same architecture as the saved one.
+	pub fn load(self, path: impl AsRef<Path>) -> Result<Self, RecorderError> {
+		let path = path.as_ref();
+		let config_path = Self::config_path(path);
+		let model = if config_path.exists() {
+			let config = ValueNetConfig::load(&*config_path.to_string_lossy())
+				.map_err(|err| RecorderError::DeserializeError(format!("{err:?}")))?;
+			Self::init_with_config(self.deepness, self.encoding(), &config)
+				.with_output_scale(self.output_scale)
+		} else {
+			self
+		};
+		model
+			.load_file(path, &NamedMpkGzFileRecorder::<FullPrecisionSettings>::new())
 			.map(Module::no_grad)
 	}
 
-	/// Save the module to a file.
-	pub fn save(self, path: impl AsRef<Path>) -> Result<(), burn::record::RecorderError> {
-		self.save_file(path.as_ref(), &NamedMpkGzFileRecorder::<FullPrecisionSettings>::new())
+	/// Load the model from a file, falling back to a fresh model with the given
+	/// deepness if loading fails. The error is returned alongside the fresh
+	/// model, so that callers can report it.
+	#[must_use]
+	pub fn load_or_init(path: impl AsRef<Path>, deepness: usize) -> (Self, Option<RecorderError>) {
+		match Self::init(deepness).load(path) {
+			Ok(model) => (model, None),
+			Err(err) => (Self::init(deepness), Some(err)),
+		}
 	}
 
-	/// Run model prediction.
-	fn forward(&self, field: Tensor<B, 3>) -> Tensor<B, 2> {
-		let [batch, height, width] = field.dims();
-		let data = field.reshape([batch, 1, height, width]);
-		let data = self.conv1.forward(data);
-		let data = tanh(data);
+	/// Save the module to a file, along with its architecture configuration.
+	pub fn save(self, path: impl AsRef<Path>) -> Result<(), RecorderError> {
+		let path = path.as_ref();
+		self.config()
+			.save(&*Self::config_path(path).to_string_lossy())
+			.map_err(|err| RecorderError::Unknown(err.to_string()))?;
+		self.save_file(path, &NamedMpkGzFileRecorder::<FullPrecisionSettings>::new())
+	}
+
+	/// Get the input encoding of the model.
+	#[must_use]
+	pub fn encoding(&self) -> InputEncoding {
+		InputEncoding::from_planes(self.input_planes)
+	}
+
+	/// Run model prediction. Takes the fields in shape [batch, planes, height,
+	/// width].
+	fn forward(&self, field: Tensor<B, 4>) -> Tensor<B, 2> {
+		let data = self.conv1.forward(field);
+		let mut data = tanh(data);
+		for conv in self.hidden_convs.iter().flatten() {
+			let output = tanh(conv.forward(data.clone()));
+			data = if self.residual { data + output } else { output };
+		}
 		let [batch, channels, height, width] = data.dims();
 		let data = data.reshape([batch, channels * height * width]);
 		let data = self.linear1.forward(data);
@@ -73,34 +214,307 @@ impl<B: Backend> AiValuePlayer<B> {
 		tanh(data)
 	}
 
-	/// Convert the board to a workable tensor.
-	fn board_to_tensor(board: &Board, me: Team) -> Tensor<B, 2> {
-		let data: Vec<_> = board
-			.field()
+	/// Convert the board to a workable tensor of shape [planes, height, width].
+	fn board_to_tensor(&self, board: &Board, me: Team) -> Tensor<B, 3> {
+		let data = self.encoding().encode(board, me);
+		let (width, height) = board.dimensions();
+		Tensor::from_floats(data.as_slice()).reshape([self.input_planes, width, height]).transpose()
+	}
+
+	/// Make sure the board has the size the model was created for.
+	fn check_dimensions(&self, board: &Board) {
+		assert_eq!(
+			board.dimensions(),
+			(self.width, self.height),
+			"Board size does not match the model"
+		);
+	}
+
+	/// Convert board to a field tensor and run the model prediction.
+	#[cfg(test)]
+	fn predict(&self, board: &Board, me: Team) -> f64 {
+		self.check_dimensions(board);
+		let data = self.board_to_tensor(board, me);
+
+		let value = self
+			.forward(data.reshape([1, self.input_planes, self.height, self.width]))
+			.reshape([1]);
+		self.output_scale * value.into_scalar().elem::<f64>()
+	}
+
+	/// Evaluate a batch of board positions from the view of `me` in a single
+	/// forward pass. Returns the values in the same order as the boards.
+	#[must_use]
+	pub fn evaluate_batch(&self, boards: &[Board], me: Team) -> Vec<f64> {
+		if boards.is_empty() {
+			return Vec::new();
+		}
+
+		let data = boards
 			.iter()
-			.map(|tile| match tile {
-				None => 0.0,
-				Some(team) if *team == me => 1.0,
-				_ => -1.0,
+			.map(|board| {
+				self.check_dimensions(board);
+				self.board_to_tensor(board, me).reshape([
+					1,
+					self.input_planes,
+					self.height,
+					self.width,
+				])
 			})
 			.collect();
-		Tensor::from_floats(data.as_slice()).reshape([7, 6]).transpose()
+		let values = self.forward(Tensor::cat(data, 0)).reshape([boards.len()]);
+		values
+			.into_data()
+			.value
+			.into_iter()
+			.map(|value| self.output_scale * value.elem::<f64>())
+			.collect()
 	}
 
-	/// Convert board to a field tensor and run the model prediction.
-	fn predict(&self, board: &Board, me: Team) -> f64 {
-		assert_eq!(board.dimensions(), (7, 6));
-		let data = Self::board_to_tensor(board, me);
+	/// Expand the search tree down to the minimax deepness, collecting all
+	/// non-terminal leaf boards to be evaluated later.
+	fn expand(
+		&self,
+		board: &Board,
+		me: Team,
+		current_deepness: usize,
+		maximize: bool,
+		leaves: &mut Vec<Board>,
+	) -> SearchNode {
+		if current_deepness + 1 >= self.deepness {
+			leaves.push(*board);
+			return SearchNode::Leaf(leaves.len() - 1);
+		}
+
+		let team = if maximize { me } else { me.other() };
+		let children = board
+			.moves_center_ordered()
+			.into_iter()
+			.map(|column| {
+				let mut test_board = *board;
+				test_board.put_tile(column, team).expect("Possible move was in fact impossible");
+
+				match test_board.game_result_on_change(column) {
+					Some(GameResult::Draw) => SearchNode::Value(0.0),
+					Some(GameResult::Winner(winner)) => {
+						SearchNode::Value(if winner == me { f64::MAX } else { f64::MIN })
+					}
+					None => self.expand(&test_board, me, current_deepness + 1, !maximize, leaves),
+				}
+			})
+			.collect();
+
+		if maximize {
+			SearchNode::Max(children)
+		} else {
+			SearchNode::Min(children)
+		}
+	}
+}
 
-		let value = self.forward(data.reshape([1, 6, 7])).reshape([1]);
-		value.into_scalar().elem()
+/// Node of the minimax search tree of the batched search.
+#[derive(Debug)]
+enum SearchNode {
+	/// Known value, i.e. a terminal position.
+	Value(f64),
+	/// Leaf position, referring to the index in the batch of evaluated boards.
+	Leaf(usize),
+	/// Our turn, take the best value out of the children.
+	Max(Vec<SearchNode>),
+	/// Other player's turn, take the worst value out of the children.
+	Min(Vec<SearchNode>),
+}
+
+impl SearchNode {
+	/// Back up the value of this node, given the evaluated leaf values.
+	fn value(&self, leaf_values: &[f64]) -> f64 {
+		match self {
+			Self::Value(value) => *value,
+			Self::Leaf(index) => leaf_values[*index],
+			Self::Max(children) => children
+				.iter()
+				.map(|child| child.value(leaf_values))
+				.max_by(nan_worst_for_max)
+				.expect("No possible moves"),
+			Self::Min(children) => children
+				.iter()
+				.map(|child| child.value(leaf_values))
+				.min_by(nan_worst_for_min)
+				.expect("No possible moves"),
+		}
+	}
+}
+
+impl<B: Backend> AiValuePlayer<B> {
+	/// Compute the minimax values of all possible moves, see
+	/// [`make_move`](Player::make_move) for how the search works.
+	fn move_values(&self, board: &Board, me: Team) -> Vec<(usize, f64)> {
+		let mut leaves = Vec::new();
+		let root = board
+			.moves_center_ordered()
+			.into_iter()
+			.map(|column| {
+				let mut test_board = *board;
+				test_board.put_tile(column, me).expect("Possible move was in fact impossible");
+
+				let node = match test_board.game_result_on_change(column) {
+					Some(GameResult::Draw) => SearchNode::Value(0.0),
+					Some(GameResult::Winner(winner)) => {
+						SearchNode::Value(if winner == me { f64::MAX } else { f64::MIN })
+					}
+					None => self.expand(&test_board, me, 1, false, &mut leaves),
+				};
+				(column, node)
+			})
+			.collect::<Vec<_>>();
+
+		let leaf_values = self.evaluate_batch(&leaves, me);
+		root.into_iter().map(|(column, node)| (column, node.value(&leaf_values))).collect()
 	}
 }
 
 impl<B: Backend> Player for AiValuePlayer<B> {
+	/// Minimax search using the model as heuristic. Unlike the plain
+	/// [`MinimaxPlayer`](crate::MinimaxPlayer), which evaluates one leaf at a
+	/// time, the search is done in evaluate-then-backup order: the whole tree
+	/// is expanded first, all leaf positions are evaluated in a single batched
+	/// forward pass and the values are backed up afterwards. This reduces the
+	/// tensor operation overhead a lot, but keeps the full tree in memory.
+	/// Moves are searched center first, ties go to the more central column.
 	fn make_move(&self, board: &Board, me: Team) -> usize {
-		let heuristic = |b: &Board, m: Team| self.predict(b, m);
-		let minimax = MinimaxPlayer::new(self.deepness, &heuristic);
-		minimax.make_move(board, me)
+		self.move_values(board, me)
+			.into_iter()
+			.reduce(
+				|best, next| if nan_worst_for_max(&next.1, &best.1).is_gt() { next } else { best },
+			)
+			.expect("No possible move")
+			.0
+	}
+
+	fn scored_moves(&self, board: &Board, me: Team) -> Option<Vec<(usize, f64)>> {
+		let mut move_values = self.move_values(board, me);
+		move_values.sort_unstable_by_key(|(column, _)| *column);
+		Some(move_values)
+	}
+
+	fn name(&self) -> &str {
+		"value-net"
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used, clippy::float_cmp)]
+
+	use super::*;
+	use crate::{MinimaxPlayer, NdArrayBackend};
+
+	#[test]
+	fn batched_search_matches_per_leaf_search() {
+		let model = AiValuePlayer::<NdArrayBackend>::init(3);
+		let mut board = Board::default();
+		board.put_tile(3, Team::X).unwrap();
+		board.put_tile(2, Team::O).unwrap();
+		board.put_tile(3, Team::X).unwrap();
+
+		let heuristic = |b: &Board, m: Team| model.predict(b, m);
+		let per_leaf = MinimaxPlayer::new(model.deepness, &heuristic);
+		assert_eq!(model.make_move(&board, Team::O), per_leaf.make_move(&board, Team::O));
+	}
+
+	#[test]
+	fn two_plane_encoding() {
+		let model =
+			AiValuePlayer::<NdArrayBackend>::init_with_encoding(1, InputEncoding::TwoPlanes);
+		let mut board = Board::default();
+		board.put_tile(3, Team::X).unwrap();
+		board.put_tile(4, Team::O).unwrap();
+		board.put_tile(4, Team::X).unwrap();
+
+		let tensor = model.board_to_tensor(&board, Team::X);
+		assert_eq!(tensor.dims(), [2, 6, 7]);
+		let data = tensor.into_data().value;
+		let at = |plane: usize, y: usize, x: usize| data[plane * 6 * 7 + y * 7 + x];
+		for plane in 0..2 {
+			for y in 0..6 {
+				for x in 0..7 {
+					let expected = match (plane, y, x) {
+						(0, 0, 3) | (0, 1, 4) | (1, 0, 4) => 1.0,
+						_ => 0.0,
+					};
+					assert_eq!(at(plane, y, x), expected, "plane {plane}, y {y}, x {x}");
+				}
+			}
+		}
+
+		// The model can predict with the encoding.
+		let value = model.predict(&board, Team::X);
+		assert!((-1.0..=1.0).contains(&value));
+	}
+
+	#[test]
+	fn deeper_network() {
+		let shallow = AiValuePlayer::<NdArrayBackend>::init(1);
+		let config = ValueNetConfig::new().with_conv_layers(3).with_residual(true);
+		let deep = AiValuePlayer::<NdArrayBackend>::init_with_config(
+			1,
+			InputEncoding::SinglePlane,
+			&config,
+		);
+		assert!(deep.num_params() > shallow.num_params());
+
+		let mut board = Board::default();
+		board.put_tile(3, Team::X).unwrap();
+		board.put_tile(4, Team::O).unwrap();
+		for value in deep.evaluate_batch(&[Board::default(), board], Team::X) {
+			assert!((-1.0..=1.0).contains(&value));
+		}
+	}
+
+	#[test]
+	fn load_restores_architecture() {
+		let dir = std::env::temp_dir().join("connect-four-value-net-test");
+		let path = dir.join("model");
+		let config = ValueNetConfig::new().with_conv_layers(3).with_residual(true);
+		let deep = AiValuePlayer::<NdArrayBackend>::init_with_config(
+			1,
+			InputEncoding::SinglePlane,
+			&config,
+		);
+		deep.clone().save(&path).unwrap();
+		let loaded = AiValuePlayer::<NdArrayBackend>::init(1).load(&path).unwrap();
+		std::fs::remove_dir_all(&dir).unwrap();
+
+		assert_eq!(loaded.config().conv_layers, 3);
+		assert!(loaded.config().residual);
+		assert_eq!(loaded.num_params(), deep.num_params());
+		let mut board = Board::default();
+		board.put_tile(3, Team::X).unwrap();
+		assert_eq!(loaded.predict(&board, Team::O), deep.predict(&board, Team::O));
+	}
+
+	#[test]
+	fn terminal_wins_rank_above_scaled_evaluations() {
+		let model = AiValuePlayer::<NdArrayBackend>::init(2).with_output_scale(1e300);
+		let mut board = Board::default();
+		for column in [0, 1, 2] {
+			board.put_tile(column, Team::X).unwrap();
+			board.put_tile(column, Team::O).unwrap();
+		}
+
+		for value in model.evaluate_batch(&[Board::default(), board], Team::X) {
+			assert!(value.abs() <= 1e300);
+		}
+		assert_eq!(model.make_move(&board, Team::X), 3);
+	}
+
+	#[test]
+	fn load_or_init_falls_back_to_fresh_model() {
+		let (model, err) =
+			AiValuePlayer::<NdArrayBackend>::load_or_init("./does/not/exist/model", 2);
+		assert!(err.is_some());
+		assert_eq!(model.deepness, 2);
+		let board = Board::default();
+		assert!(board.is_legal_move(model.make_move(&board, Team::X)));
 	}
 }
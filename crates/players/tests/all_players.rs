@@ -0,0 +1,34 @@
+//! Play a game between each ordered pair of player types, making sure every
+//! player can complete games against every other player.
+#![allow(missing_docs, clippy::missing_docs_in_private_items)]
+
+use game::{Game, Player};
+use players::{AiPolicyPlayer, AiValuePlayer, MinimaxPlayer, NdArrayBackend, SeededRandomPlayer};
+
+/// Create one player of each type.
+fn all_players() -> Vec<(&'static str, Box<dyn Player>)> {
+	vec![
+		("random", Box::new(SeededRandomPlayer::new(42))),
+		("minimax", Box::new(MinimaxPlayer::new_1(2))),
+		("value", Box::new(AiValuePlayer::<NdArrayBackend>::init(1))),
+		("policy", Box::new(AiPolicyPlayer::<NdArrayBackend>::init())),
+	]
+}
+
+#[test]
+fn every_pair_completes_a_game() {
+	let players = all_players();
+	for (name_x, player_x) in &players {
+		for (name_o, player_o) in &players {
+			let mut game = Game::builder().player_x(player_x).player_o(player_o).build();
+			let result = game.run_error_loss();
+			// Games lost by an illegal move end without a result on the board.
+			let board_result = game.board().game_result();
+			assert!(
+				board_result.is_none_or(|board_result| board_result == result),
+				"{name_x} vs {name_o}"
+			);
+			assert!(!game.history().is_empty(), "{name_x} vs {name_o}");
+		}
+	}
+}
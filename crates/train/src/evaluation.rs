@@ -1,13 +1,19 @@
 //! Implementation of model evaluation, evaluating a whole population.
 
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 
-use game::{Game, GameResult, Player, Team};
-use players::{MinimaxPlayer, RandomPlayer};
-use rayon::prelude::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use burn::tensor::backend::Backend;
+use game::{Board, Game, GameRecord, GameResult, Player, Team};
+use players::{AiValuePlayer, MinimaxPlayer, SeededRandomPlayer, SolverPlayer};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use rayon::{
+	prelude::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator},
+	ThreadPool,
+};
 
 /// Evaluator interface that evaluators and evaluation functions implement to
-/// determine performance of the population's models.
+/// determine performance of the population's models. Heterogeneous sets of
+/// players can be evaluated as `Box<dyn Player + Send + Sync>` models.
 pub trait Evaluator<Model>
 where
 	Model: Player,
@@ -26,9 +32,247 @@ where
 	}
 }
 
+/// Evaluator wrapper running the inner evaluator on a specific rayon thread
+/// pool instead of the global one, e.g. to limit the number of threads used.
+#[derive(Debug)]
+pub struct ThreadPoolEvaluator<Eval> {
+	/// Inner evaluator.
+	inner: Eval,
+	/// Thread pool to run the evaluation on.
+	pool: Arc<ThreadPool>,
+}
+
+impl<Eval> ThreadPoolEvaluator<Eval> {
+	/// Run the evaluator on the given thread pool.
+	#[must_use]
+	pub fn new(inner: Eval, pool: Arc<ThreadPool>) -> Self {
+		Self { inner, pool }
+	}
+
+	/// Get the inner evaluator mutably, e.g. to update its configuration.
+	pub fn inner_mut(&mut self) -> &mut Eval {
+		&mut self.inner
+	}
+}
+
+impl<Model, Eval> Evaluator<Model> for ThreadPoolEvaluator<Eval>
+where
+	Model: Player + Sync,
+	Eval: Evaluator<Model> + Send,
+{
+	fn evaluate(&mut self, models: &[Model]) -> Vec<f32> {
+		let inner = &mut self.inner;
+		self.pool.install(|| inner.evaluate(models))
+	}
+}
+
+/// Play a single game between two players, starting from the given board
+/// position. Player errors are converted to losses.
+pub fn play_match(player_x: &dyn Player, player_o: &dyn Player, start: Board) -> GameResult {
+	let mut game = Game::builder().player_x(player_x).player_o(player_o).board(start).build();
+	game.run_error_loss()
+}
+
+/// Play a single game between two players from the empty board and return
+/// the full record of it, to be able to inspect how the game was won or lost.
+/// Player errors are converted to losses.
+pub fn play_traced(player_x: &dyn Player, player_o: &dyn Player) -> GameRecord {
+	play_recorded(player_x, player_o, Board::default())
+}
+
+/// Play a single game between two players, starting from the given board
+/// position, and return the record of it. Player errors are converted to
+/// losses.
+fn play_recorded(player_x: &dyn Player, player_o: &dyn Player, start: Board) -> GameRecord {
+	let mut game = Game::builder().player_x(player_x).player_o(player_o).board(start).build();
+	let result = game.run_error_loss();
+	GameRecord { start, moves: game.history().to_vec(), result }
+}
+
+/// Measure the win rates of the model against the opponent separately when
+/// playing as X and as O, playing the given number of games per color. A big
+/// gap between the two rates indicates a model that overfit to one side.
+/// Returns `(win_rate_as_x, win_rate_as_o)`.
+pub fn color_balance<M>(model: &M, opponent: &(impl Player + Sync), games: usize) -> (f32, f32)
+where
+	M: Player + Sync,
+{
+	if games == 0 {
+		return (0.0, 0.0);
+	}
+
+	let wins_as = |team: Team| {
+		(0..games)
+			.into_par_iter()
+			.filter(|_| {
+				let result = match team {
+					Team::X => play_match(model, opponent, Board::default()),
+					Team::O => play_match(opponent, model, Board::default()),
+				};
+				result == GameResult::Winner(team)
+			})
+			.count()
+	};
+	(wins_as(Team::X) as f32 / games as f32, wins_as(Team::O) as f32 / games as f32)
+}
+
+/// Find the positions where the model's move differs from the solver's move,
+/// e.g. to oversample them in supervised training. The side to move is derived
+/// from each position. Returns `(position, model_move, solver_move)` for every
+/// disagreement, in the order of the positions.
+pub fn solver_disagreements<M: Player + Sync>(
+	model: &M,
+	positions: &[Board],
+	solver: &SolverPlayer,
+) -> Vec<(Board, usize, usize)> {
+	positions
+		.par_iter()
+		.filter_map(|position| {
+			let me = position.whos_turn();
+			let model_move = model.make_move(position, me);
+			let solver_move = solver.make_move(position, me);
+			(model_move != solver_move).then_some((*position, model_move, solver_move))
+		})
+		.collect()
+}
+
+/// Measure how often the value network's pure value judgement agrees with
+/// the move of a minimax player with heuristic 1 searching at the given depth.
+/// The network picks the move leading to the position it values most, without
+/// any search. The side to move is derived from each position, which must not
+/// be finished. Returns the fraction of agreeing positions, 0 if there are
+/// none.
+pub fn value_net_vs_minimax_agreement<B: Backend>(
+	model: &AiValuePlayer<B>,
+	depth: usize,
+	positions: &[Board],
+) -> f32 {
+	if positions.is_empty() {
+		return 0.0;
+	}
+
+	let minimax = MinimaxPlayer::new_1(depth);
+	let agreements = positions
+		.par_iter()
+		.filter(|position| {
+			let me = position.whos_turn();
+			let value_move =
+				position.greedy_move(me, |board, me| model.evaluate_batch(&[*board], me)[0]);
+			value_move == Some(minimax.make_move(position, me))
+		})
+		.count();
+	agreements as f32 / positions.len() as f32
+}
+
+/// Set of opening positions to start evaluation games from, to reduce the
+/// influence of the first player advantage. Each pairing of models is assigned
+/// a random, but fixed, opening from the set, so both color orders of the pair
+/// play from the same position.
+#[derive(Debug, Clone, Default)]
+pub struct Openings {
+	/// Opening positions to choose from. Games start from the empty board if
+	/// there are none.
+	positions: Vec<Board>,
+	/// Seed for choosing the opening of a pairing.
+	seed: u64,
+}
+
+impl Openings {
+	/// Create a new set of opening positions with the seed to assign them to
+	/// pairings.
+	#[must_use]
+	pub fn new(positions: Vec<Board>, seed: u64) -> Self {
+		Self { positions, seed }
+	}
+
+	/// Get the opening position for the pairing of the models with index `a`
+	/// and `b`. It is the same regardless of the order of `a` and `b`.
+	#[must_use]
+	pub fn for_pair(&self, a: usize, b: usize) -> Board {
+		let (low, high) = (a.min(b) as u64, a.max(b) as u64);
+		let pair_seed = self
+			.seed
+			.wrapping_add(low.wrapping_mul(0x9E37_79B9_7F4A_7C15))
+			.wrapping_add(high.wrapping_mul(0xC2B2_AE3D_27D4_EB4F));
+		let mut rng = StdRng::seed_from_u64(pair_seed);
+		self.positions.choose(&mut rng).copied().unwrap_or_default()
+	}
+}
+
+/// Scoring scheme of [`league_scores_with_openings`]. The default scores a
+/// win with 1, a loss with -1 and a draw with 0, without move penalty and
+/// normalization.
+#[derive(Debug, Clone, Copy, PartialEq, typed_builder::TypedBuilder)]
+pub struct ScoringConfig {
+	/// Score both players get for a draw.
+	#[builder(default = 0.0)]
+	draw: f32,
+	/// Whether to divide the scores by the number of games each model played,
+	/// so that the population size does not inflate the scores.
+	#[builder(default = false)]
+	normalize: bool,
+	/// Penalty per move played in the game, so that faster wins score higher
+	/// than slower ones. Symmetrically, slower losses score higher than faster
+	/// ones. It should be small enough to keep wins above draws.
+	#[builder(default = 0.0)]
+	move_penalty: f32,
+}
+
+impl Default for ScoringConfig {
+	fn default() -> Self {
+		Self::builder().build()
+	}
+}
+
+impl ScoringConfig {
+	/// Get the score for a draw.
+	#[must_use]
+	pub fn draw(&self) -> f32 {
+		self.draw
+	}
+
+	/// Get whether the scores are normalized by the number of games played.
+	#[must_use]
+	pub fn normalize(&self) -> bool {
+		self.normalize
+	}
+
+	/// Get the penalty per move played.
+	#[must_use]
+	pub fn move_penalty(&self) -> f32 {
+		self.move_penalty
+	}
+
+	/// Score a game for the team, taking the number of moves played into
+	/// account for wins and losses.
+	#[must_use]
+	pub fn score(&self, record: &GameRecord, team: Team) -> f32 {
+		let penalty = self.move_penalty * record.moves.len() as f32;
+		match record.result {
+			GameResult::Winner(winner) if winner == team => 1.0 - penalty,
+			GameResult::Winner(_) => penalty - 1.0,
+			GameResult::Draw => self.draw,
+		}
+	}
+}
+
 /// Evaluation function for a set of models. Run games between each of the
 /// leagues participants and return their scores.
 pub fn league_scores<Model>(models: &[Model]) -> Vec<f32>
+where
+	Model: Player + Send + Sync,
+{
+	league_scores_with_openings(models, &Openings::default(), &ScoringConfig::default())
+}
+
+/// Evaluation function for a set of models. Run games between each of the
+/// leagues participants, starting from the pairing's opening position, and
+/// return their scores according to the scoring scheme.
+pub fn league_scores_with_openings<Model>(
+	models: &[Model],
+	openings: &Openings,
+	scoring: &ScoringConfig,
+) -> Vec<f32>
 where
 	Model: Player + Send + Sync,
 {
@@ -39,22 +283,26 @@ where
 		}
 	}
 
-	let scores = Mutex::new(vec![0.0; models.len()]);
-	matchups.into_par_iter().for_each(|(i, j)| {
-		let mut game = Game::builder().player_x(&models[i]).player_o(&models[j]).build();
-		let result = game.run_error_loss();
-		if let GameResult::Winner(winner) = result {
-			let mut scores = scores.lock().expect("lock poisened");
-			if winner == Team::X {
-				scores[i] += 1.0;
-				scores[j] -= 1.0;
-			} else {
-				scores[i] -= 1.0;
-				scores[j] += 1.0;
-			}
+	// Play in parallel, but sum up the scores afterwards without any locking.
+	let results = matchups
+		.into_par_iter()
+		.map(|(i, j)| (i, j, play_recorded(&models[i], &models[j], openings.for_pair(i, j))))
+		.collect::<Vec<_>>();
+
+	let mut scores = vec![0.0; models.len()];
+	for (i, j, record) in results {
+		scores[i] += scoring.score(&record, Team::X);
+		scores[j] += scoring.score(&record, Team::O);
+	}
+
+	if scoring.normalize {
+		// Every model plays each model, including itself, once as X and once as O.
+		let games = (2 * models.len()) as f32;
+		for score in &mut scores {
+			*score /= games;
 		}
-	});
-	scores.into_inner().expect("lock poisened")
+	}
+	scores
 }
 
 /// Evaluation function for a set of models. Run games against the random
@@ -70,14 +318,22 @@ where
 }
 
 /// Evaluator for a set of models. Run games against a random player, minimax
-/// player and a set of previous models.
+/// player and a set of previous models. The best model is frozen into the set
+/// of previous models every few evaluations, dropping the oldest ones when the
+/// set grows too big.
 #[derive(Debug)]
 pub struct PlayerPlusEvaluator<Model>
 where
 	Model: Player + Clone + Send + Sync,
 {
-	/// Set of previous models to also test against.
+	/// Set of previous models to also test against, oldest first.
 	previous: Vec<Model>,
+	/// Freeze the best model every this many evaluations.
+	freeze_every: usize,
+	/// Maximum number of previous models to keep, unlimited if `None`.
+	max_previous: Option<usize>,
+	/// Number of evaluations run so far.
+	evaluations: usize,
 }
 
 impl<Model> Evaluator<Model> for PlayerPlusEvaluator<Model>
@@ -115,14 +371,7 @@ where
 			})
 			.collect::<Vec<_>>();
 
-		if let Some((max_index, _max)) = scores
-			.iter()
-			.enumerate()
-			.max_by(|(_, a), (_, b)| a.partial_cmp(b).expect("Score was NaN"))
-		{
-			self.previous.push(models[max_index].clone());
-		}
-
+		self.freeze_best(models, &scores);
 		scores
 	}
 }
@@ -132,7 +381,7 @@ where
 	Model: Player + Clone + Send + Sync,
 {
 	fn default() -> Self {
-		Self { previous: Vec::new() }
+		Self { previous: Vec::new(), freeze_every: 1, max_previous: None, evaluations: 0 }
 	}
 }
 
@@ -143,28 +392,468 @@ where
 	/// Add a "previous" model to the set so that it is used in evaluation.
 	#[must_use]
 	pub fn with_model(mut self, model: Model) -> Self {
-		self.previous.push(model);
+		self.push_previous(model);
 		self
 	}
 
 	/// Add a "previous" model to the set so that it is used in evaluation.
 	pub fn add_model(&mut self, model: Model) -> &mut Self {
-		self.previous.push(model);
+		self.push_previous(model);
 		self
 	}
 
+	/// Only freeze the best model every `freeze_every` evaluations instead of
+	/// after every evaluation. Values below 1 are treated as 1.
+	#[must_use]
+	pub fn with_freeze_every(mut self, freeze_every: usize) -> Self {
+		self.freeze_every = freeze_every.max(1);
+		self
+	}
+
+	/// Keep at most `max_previous` previous models, dropping the oldest ones
+	/// when more are added.
+	#[must_use]
+	pub fn with_max_previous(mut self, max_previous: usize) -> Self {
+		self.max_previous = Some(max_previous);
+		self.truncate_previous();
+		self
+	}
+
+	/// Get the number of previous models currently tested against.
+	#[must_use]
+	pub fn previous_len(&self) -> usize {
+		self.previous.len()
+	}
+
+	/// Count the evaluation and freeze the best model into the previous models
+	/// if it is time to.
+	fn freeze_best(&mut self, models: &[Model], scores: &[f32]) {
+		self.evaluations += 1;
+		if !self.evaluations.is_multiple_of(self.freeze_every) {
+			return;
+		}
+		if let Some((max_index, _max)) = scores
+			.iter()
+			.enumerate()
+			.filter(|(_, score)| !score.is_nan())
+			.max_by(|(_, a), (_, b)| a.total_cmp(b))
+		{
+			self.push_previous(models[max_index].clone());
+		}
+	}
+
+	/// Add a model to the previous models, respecting the maximum size.
+	fn push_previous(&mut self, model: Model) {
+		self.previous.push(model);
+		self.truncate_previous();
+	}
+
+	/// Drop the oldest previous models until the maximum size is respected.
+	fn truncate_previous(&mut self) {
+		if let Some(max_previous) = self.max_previous {
+			let excess = self.previous.len().saturating_sub(max_previous);
+			self.previous.drain(..excess);
+		}
+	}
+
 	// TODO: Load and save..
 }
 
-/// Test the performance of the model against the random player.
+/// Player wrapper that counts the columns the wrapped model plays in, to be
+/// able to detect degenerate strategies.
+#[derive(Debug)]
+pub struct MoveCounter<Model>
+where
+	Model: Player,
+{
+	/// The wrapped model.
+	model: Model,
+	/// Number of moves played per column.
+	counts: Mutex<Vec<usize>>,
+}
+
+impl<Model> MoveCounter<Model>
+where
+	Model: Player,
+{
+	/// Wrap a model to count its moves.
+	#[must_use]
+	pub fn new(model: Model) -> Self {
+		Self { model, counts: Mutex::new(Vec::new()) }
+	}
+
+	/// Get the wrapped model.
+	pub fn model(&self) -> &Model {
+		&self.model
+	}
+
+	/// Normalized entropy of the distribution of played columns, between 0.0
+	/// (always the same column) and 1.0 (all columns equally often). Returns
+	/// 1.0 if no moves were played.
+	pub fn entropy(&self) -> f32 {
+		let counts = self.counts.lock().expect("lock poisened");
+		let total = counts.iter().sum::<usize>();
+		if total == 0 || counts.len() < 2 {
+			return 1.0;
+		}
+
+		let entropy = counts
+			.iter()
+			.filter(|count| **count > 0)
+			.map(|count| {
+				let p = *count as f32 / total as f32;
+				-p * p.ln()
+			})
+			.sum::<f32>();
+		entropy / (counts.len() as f32).ln()
+	}
+}
+
+impl<Model> Player for MoveCounter<Model>
+where
+	Model: Player,
+{
+	fn make_move(&self, board: &Board, me: Team) -> usize {
+		let column = self.model.make_move(board, me);
+		let mut counts = self.counts.lock().expect("lock poisened");
+		let (width, _) = board.dimensions();
+		if counts.len() < width {
+			counts.resize(width, 0);
+		}
+		if let Some(count) = counts.get_mut(column) {
+			*count += 1;
+		}
+		column
+	}
+
+	fn should_resign(&self, board: &Board, me: Team) -> bool {
+		self.model.should_resign(board, me)
+	}
+
+	fn scored_moves(&self, board: &Board, me: Team) -> Option<Vec<(usize, f64)>> {
+		self.model.scored_moves(board, me)
+	}
+
+	fn new_game(&self) {
+		self.model.new_game();
+	}
+
+	fn name(&self) -> &str {
+		self.model.name()
+	}
+}
+
+/// Evaluator wrapper that penalizes degenerate play. The inner evaluator scores
+/// the models wrapped in [`MoveCounter`]s, then a penalty proportional to the
+/// lack of move entropy is subtracted from each score.
+#[derive(Debug)]
+pub struct EntropyPenaltyEvaluator<Eval> {
+	/// Inner evaluator to compute the base scores.
+	inner: Eval,
+	/// Weight of the penalty. A model always playing the same column loses
+	/// this much score.
+	weight: f32,
+}
+
+impl<Eval> EntropyPenaltyEvaluator<Eval> {
+	/// Wrap an evaluator to penalize low move entropy with the given weight.
+	#[must_use]
+	pub fn new(inner: Eval, weight: f32) -> Self {
+		Self { inner, weight }
+	}
+}
+
+impl<Model, Eval> Evaluator<Model> for EntropyPenaltyEvaluator<Eval>
+where
+	Model: Player + Clone,
+	Eval: Evaluator<MoveCounter<Model>>,
+{
+	fn evaluate(&mut self, models: &[Model]) -> Vec<f32> {
+		let counted = models.iter().cloned().map(MoveCounter::new).collect::<Vec<_>>();
+		let scores = self.inner.evaluate(&counted);
+		scores
+			.into_iter()
+			.zip(&counted)
+			.map(|(score, model)| (-self.weight).mul_add(1.0 - model.entropy(), score))
+			.collect()
+	}
+}
+
+/// Evaluator measuring how consistently models perform. Each model plays a
+/// number of matches against a fixed opponent, each match consisting of one
+/// game per color. The opponent is created per match from a seed, so seeded
+/// stochastic opponents play the same games for every model. The score of a
+/// model is its mean match score, reduced by its variance times the penalty.
+#[derive(Debug, Clone)]
+pub struct ConsistencyEvaluator<F> {
+	/// Function creating the opponent from a seed.
+	opponent: F,
+	/// Number of matches to play per model.
+	matches: usize,
+	/// Base seed of the opponents, offset by the match index.
+	seed: u64,
+	/// Weight of the variance penalty.
+	variance_penalty: f32,
+}
+
+impl<F, P> ConsistencyEvaluator<F>
+where
+	F: Fn(u64) -> P,
+	P: Player,
+{
+	/// Create a new consistency evaluator playing the given number of matches
+	/// against opponents created from the seed. The variance is not penalized
+	/// by default.
+	#[must_use]
+	pub fn new(opponent: F, matches: usize, seed: u64) -> Self {
+		Self { opponent, matches, seed, variance_penalty: 0.0 }
+	}
+
+	/// Set the weight of the variance penalty.
+	#[must_use]
+	pub fn with_variance_penalty(mut self, variance_penalty: f32) -> Self {
+		self.variance_penalty = variance_penalty;
+		self
+	}
+
+	/// Compute the mean and variance of the match scores of the model. A match
+	/// scores 1 for winning both games, -1 for losing both and values in
+	/// between otherwise.
+	pub fn score_stats(&self, model: &impl Player) -> (f32, f32) {
+		if self.matches == 0 {
+			return (0.0, 0.0);
+		}
+
+		let scores = (0..self.matches)
+			.map(|i| {
+				let seed = self.seed.wrapping_add(i as u64);
+				let as_x = match play_match(model, &(self.opponent)(seed), Board::default()) {
+					GameResult::Winner(Team::X) => 1.0,
+					GameResult::Winner(Team::O) => -1.0,
+					GameResult::Draw => 0.0,
+				};
+				let as_o = match play_match(&(self.opponent)(seed), model, Board::default()) {
+					GameResult::Winner(Team::X) => -1.0,
+					GameResult::Winner(Team::O) => 1.0,
+					GameResult::Draw => 0.0,
+				};
+				(as_x + as_o) / 2.0
+			})
+			.collect::<Vec<f32>>();
+
+		let mean = scores.iter().sum::<f32>() / scores.len() as f32;
+		let variance =
+			scores.iter().map(|score| (score - mean).powi(2)).sum::<f32>() / scores.len() as f32;
+		(mean, variance)
+	}
+}
+
+impl<Model, F, P> Evaluator<Model> for ConsistencyEvaluator<F>
+where
+	Model: Player + Sync,
+	F: Fn(u64) -> P + Sync,
+	P: Player,
+{
+	fn evaluate(&mut self, models: &[Model]) -> Vec<f32> {
+		models
+			.par_iter()
+			.map(|model| {
+				let (mean, variance) = self.score_stats(model);
+				(-self.variance_penalty).mul_add(variance, mean)
+			})
+			.collect()
+	}
+}
+
+/// Expected score of a player rated `rating_a` against a player rated
+/// `rating_b`, following the Elo rating system. A win counts 1, a draw 0.5.
+#[must_use]
+pub fn expected_score(rating_a: f64, rating_b: f64) -> f64 {
+	1.0 / (1.0 + 10.0_f64.powf((rating_b - rating_a) / 400.0))
+}
+
+/// Rating difference that corresponds to the expected `score` (between 0 and
+/// 1), i.e. the inverse of [`expected_score`]. Scores of 0 and 1 result in
+/// infinite differences.
+#[must_use]
+pub fn rating_diff_from_score(score: f64) -> f64 {
+	400.0 * (score / (1.0 - score)).log10()
+}
+
+/// Evaluator rating models with the Elo system. All models play each other
+/// once with each color, then the ratings, starting at 0, are updated with the
+/// game results. The update is repeated over the same results a number of
+/// passes to reduce the dependence on the order of the games.
+#[derive(Debug, Clone)]
+pub struct EloEvaluator {
+	/// Maximum rating change per game.
+	k_factor: f64,
+	/// Number of update passes over the game results.
+	passes: usize,
+}
+
+impl Default for EloEvaluator {
+	fn default() -> Self {
+		Self { k_factor: 16.0, passes: 10 }
+	}
+}
+
+impl EloEvaluator {
+	/// Create a new Elo evaluator with the given K-factor and number of update
+	/// passes.
+	#[must_use]
+	pub fn new(k_factor: f64, passes: usize) -> Self {
+		Self { k_factor, passes }
+	}
+}
+
+impl<Model> Evaluator<Model> for EloEvaluator
+where
+	Model: Player + Send + Sync,
+{
+	fn evaluate(&mut self, models: &[Model]) -> Vec<f32> {
+		let mut matchups = Vec::new();
+		for i in 0..models.len() {
+			for j in 0..models.len() {
+				if i != j {
+					matchups.push((i, j));
+				}
+			}
+		}
+
+		let results = matchups
+			.into_par_iter()
+			.map(|(i, j)| (i, j, play_match(&models[i], &models[j], Board::default())))
+			.collect::<Vec<_>>();
+
+		let mut ratings = vec![0.0; models.len()];
+		for _ in 0..self.passes {
+			for (i, j, result) in &results {
+				let score_x = match result {
+					GameResult::Winner(Team::X) => 1.0,
+					GameResult::Winner(Team::O) => 0.0,
+					GameResult::Draw => 0.5,
+				};
+				let delta = self.k_factor * (score_x - expected_score(ratings[*i], ratings[*j]));
+				ratings[*i] += delta;
+				ratings[*j] -= delta;
+			}
+		}
+		ratings.into_iter().map(|rating| rating as f32).collect()
+	}
+}
+
+/// Evaluator with a curriculum, ramping up the opponent strength with the
+/// training progress. Models are scored by a weighted sum of the scores
+/// against the random player and the minimax player. Over the schedule, the
+/// minimax depth increases from the minimum to the maximum and the weight of
+/// the random games decreases from the start to the end weight, so that the
+/// scores stay informative throughout training. The current training step
+/// must be set via [`CurriculumEvaluator::set_step`] before each training
+/// step.
+#[derive(Debug, Clone, typed_builder::TypedBuilder)]
+pub struct CurriculumEvaluator {
+	/// Current training step.
+	#[builder(default)]
+	step: usize,
+	/// Number of training steps until the maximum difficulty is reached.
+	total_steps: usize,
+	/// Minimax depth at the start of the schedule.
+	#[builder(default = 1)]
+	min_depth: usize,
+	/// Minimax depth at the end of the schedule.
+	#[builder(default = 5)]
+	max_depth: usize,
+	/// Weight of the random games at the start of the schedule, between 0
+	/// and 1. The minimax games make up the rest.
+	#[builder(default = 0.8)]
+	random_weight_start: f32,
+	/// Weight of the random games at the end of the schedule, between 0 and 1.
+	#[builder(default = 0.2)]
+	random_weight_end: f32,
+}
+
+impl CurriculumEvaluator {
+	/// Get the current training step.
+	#[must_use]
+	pub fn step(&self) -> usize {
+		self.step
+	}
+
+	/// Set the current training step.
+	pub fn set_step(&mut self, step: usize) -> &mut Self {
+		self.step = step;
+		self
+	}
+
+	/// Progress through the schedule, between 0 and 1.
+	#[must_use]
+	pub fn progress(&self) -> f32 {
+		if self.total_steps == 0 {
+			return 1.0;
+		}
+		(self.step as f32 / self.total_steps as f32).min(1.0)
+	}
+
+	/// Minimax depth at the current step.
+	#[must_use]
+	pub fn current_depth(&self) -> usize {
+		let range = self.max_depth.saturating_sub(self.min_depth) as f32;
+		self.min_depth + (range * self.progress()).round() as usize
+	}
+
+	/// Weight of the random games at the current step.
+	#[must_use]
+	pub fn current_random_weight(&self) -> f32 {
+		(self.random_weight_end - self.random_weight_start)
+			.mul_add(self.progress(), self.random_weight_start)
+	}
+}
+
+impl<Model> Evaluator<Model> for CurriculumEvaluator
+where
+	Model: Player + Send + Sync,
+{
+	fn evaluate(&mut self, models: &[Model]) -> Vec<f32> {
+		let depth = self.current_depth();
+		let random_weight = self.current_random_weight();
+		models
+			.par_iter()
+			.map(|model| {
+				random_weight.mul_add(
+					test_random::<_, 100>(model),
+					(1.0 - random_weight) * test_minimax_depth(model, depth),
+				)
+			})
+			.collect()
+	}
+}
+
+/// Base seed of the random opponents in [`test_random`].
+pub const RANDOM_OPPONENT_SEED: u64 = 0x00C0_FFEE;
+
+/// Test the performance of the model against the random player. Every game is
+/// played against a freshly seeded random player, so that all models face the
+/// exact same random opponents and their scores are comparable.
 pub fn test_random<Model, const N: usize>(model: &Model) -> f32
+where
+	Model: Player,
+{
+	test_random_with_seed(model, N, RANDOM_OPPONENT_SEED)
+}
+
+/// Test the performance of the model against the random player in the given
+/// number of games. The random opponent of each game is seeded from the base
+/// seed and the game index.
+pub fn test_random_with_seed<Model>(model: &Model, games: usize, base_seed: u64) -> f32
 where
 	Model: Player,
 {
 	let mut score = 0.0;
 
-	for _ in 0..N / 2 {
-		let mut game = Game::builder().player_x(&RandomPlayer).player_o(model).build();
+	for i in 0..games / 2 {
+		let opponent = random_opponent(base_seed, i);
+		let mut game = Game::builder().player_x(&opponent).player_o(model).build();
 		let result = game.run_error_loss();
 		match result {
 			GameResult::Winner(Team::X) => score -= 1.0,
@@ -173,8 +862,9 @@ where
 		}
 	}
 
-	for _ in 0..N / 2 {
-		let mut game = Game::builder().player_x(model).player_o(&RandomPlayer).build();
+	for i in games / 2..games / 2 * 2 {
+		let opponent = random_opponent(base_seed, i);
+		let mut game = Game::builder().player_x(model).player_o(&opponent).build();
 		let result = game.run_error_loss();
 		match result {
 			GameResult::Winner(Team::X) => score += 1.0,
@@ -183,16 +873,29 @@ where
 		}
 	}
 
-	score / N as f32
+	score / games as f32
+}
+
+/// Random opponent for the game with the given index.
+fn random_opponent(base_seed: u64, game: usize) -> SeededRandomPlayer {
+	SeededRandomPlayer::new(base_seed.wrapping_add(game as u64))
 }
 
 /// Test performance against the minimax player.
 pub fn test_minimax<Model, const DEEPNESS: usize>(model: &Model) -> f32
+where
+	Model: Player,
+{
+	test_minimax_depth(model, DEEPNESS)
+}
+
+/// Test performance against the minimax player with the given search depth.
+pub fn test_minimax_depth<Model>(model: &Model, deepness: usize) -> f32
 where
 	Model: Player,
 {
 	let mut score = 0.0;
-	let minimax_player = MinimaxPlayer::new_1(DEEPNESS);
+	let minimax_player = MinimaxPlayer::new_1(deepness);
 
 	for _ in 0..50 {
 		let mut game = Game::builder().player_x(model).player_o(&minimax_player).build();
@@ -216,3 +919,400 @@ where
 
 	score / 100.0
 }
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used, clippy::float_cmp)]
+
+	use players::{NdArrayBackend, RandomPlayer};
+	use rand::Rng;
+
+	use super::*;
+
+	/// Deterministic player for tests. Either always plays the first free
+	/// column or varies its column with the number of played tiles.
+	#[derive(Debug, Clone)]
+	struct ScriptedPlayer {
+		/// Whether to vary the played column.
+		varied: bool,
+	}
+
+	impl Player for ScriptedPlayer {
+		fn make_move(&self, board: &Board, _me: Team) -> usize {
+			let (width, _) = board.dimensions();
+			let tiles = board.field().iter().filter(|tile| tile.is_some()).count();
+			let start = if self.varied { tiles % width } else { 0 };
+			(start..width).chain(0..start).find(|column| board.is_legal_move(*column)).unwrap()
+		}
+	}
+
+	#[test]
+	fn evaluate_mixed_players() {
+		let players: Vec<Box<dyn Player + Send + Sync>> = vec![
+			Box::new(RandomPlayer),
+			Box::new(MinimaxPlayer::new_1(2)),
+			Box::new(ScriptedPlayer { varied: true }),
+		];
+		let scores = league_scores(&players);
+		assert_eq!(scores.len(), 3);
+		assert!(scores.iter().sum::<f32>().abs() < f32::EPSILON);
+	}
+
+	/// Player recording the boards it had to move on.
+	#[derive(Debug, Default)]
+	struct RecordingPlayer {
+		/// The boards seen so far.
+		boards: Mutex<Vec<Board>>,
+	}
+
+	impl Player for RecordingPlayer {
+		fn make_move(&self, board: &Board, me: Team) -> usize {
+			self.boards.lock().unwrap().push(*board);
+			ScriptedPlayer { varied: true }.make_move(board, me)
+		}
+	}
+
+	#[test]
+	fn openings_are_shared_by_both_color_orders() {
+		let positions = (0..5)
+			.map(|column| {
+				let mut board = Board::default();
+				board.put_tile(column, Team::X).unwrap();
+				board
+			})
+			.collect::<Vec<_>>();
+		let openings = Openings::new(positions.clone(), 42);
+
+		for (a, b) in [(0, 1), (1, 4), (2, 3), (7, 0)] {
+			let opening = openings.for_pair(a, b);
+			assert_eq!(opening, openings.for_pair(b, a));
+			assert!(positions.contains(&opening));
+
+			let (player_a, player_b) = (RecordingPlayer::default(), RecordingPlayer::default());
+			play_match(&player_a, &player_b, openings.for_pair(a, b));
+			play_match(&player_b, &player_a, openings.for_pair(b, a));
+			// O moves first after the opening move.
+			assert_eq!(player_b.boards.lock().unwrap()[0], opening);
+			assert_eq!(
+				player_a.boards.lock().unwrap().iter().filter(|b| **b == opening).count(),
+				1
+			);
+		}
+	}
+
+	#[test]
+	fn entropy_penalty_punishes_single_column_play() {
+		let models = [ScriptedPlayer { varied: false }, ScriptedPlayer { varied: true }];
+		let inner = |models: &[MoveCounter<ScriptedPlayer>]| {
+			for model in models {
+				for _ in 0..10 {
+					Game::builder()
+						.player_x(model)
+						.player_o(&RandomPlayer)
+						.build()
+						.run_error_loss();
+				}
+			}
+			vec![1.0; models.len()]
+		};
+
+		let mut evaluator = EntropyPenaltyEvaluator::new(inner, 0.5);
+		let scores = evaluator.evaluate(&models);
+		assert!(scores[0] < scores[1]);
+	}
+
+	#[test]
+	fn expected_score_is_symmetric() {
+		assert!((expected_score(1500.0, 1500.0) - 0.5).abs() < 1e-12);
+		assert!(
+			(expected_score(1200.0, 1000.0) + expected_score(1000.0, 1200.0) - 1.0).abs() < 1e-12
+		);
+		assert!(rating_diff_from_score(0.5).abs() < 1e-12);
+	}
+
+	#[test]
+	fn expected_score_for_400_points() {
+		let score = expected_score(400.0, 0.0);
+		assert!((score - 10.0 / 11.0).abs() < 1e-12);
+		assert!((score - 0.91).abs() < 0.01);
+		assert!((rating_diff_from_score(score) - 400.0).abs() < 1e-9);
+	}
+
+	#[test]
+	fn curriculum_ramps_up_difficulty() {
+		let mut evaluator =
+			CurriculumEvaluator::builder().total_steps(100).min_depth(1).max_depth(5).build();
+		assert_eq!(evaluator.current_depth(), 1);
+		assert!((evaluator.current_random_weight() - 0.8).abs() < 1e-6);
+
+		evaluator.set_step(50);
+		assert_eq!(evaluator.current_depth(), 3);
+
+		evaluator.set_step(100);
+		assert_eq!(evaluator.current_depth(), 5);
+		assert!((evaluator.current_random_weight() - 0.2).abs() < 1e-6);
+
+		evaluator.set_step(1000);
+		assert_eq!(evaluator.current_depth(), 5);
+	}
+
+	#[test]
+	fn league_scores_match_locked_implementation() {
+		let models = [
+			ScriptedPlayer { varied: false },
+			ScriptedPlayer { varied: true },
+			ScriptedPlayer { varied: false },
+			ScriptedPlayer { varied: true },
+		];
+
+		// Reference implementation with a shared, locked score vector.
+		let expected = Mutex::new(vec![0.0; models.len()]);
+		for i in 0..models.len() {
+			for j in 0..models.len() {
+				let result = play_match(&models[i], &models[j], Board::default());
+				if let GameResult::Winner(winner) = result {
+					let mut scores = expected.lock().unwrap();
+					if winner == Team::X {
+						scores[i] += 1.0;
+						scores[j] -= 1.0;
+					} else {
+						scores[i] -= 1.0;
+						scores[j] += 1.0;
+					}
+				}
+			}
+		}
+
+		assert_eq!(league_scores(&models), expected.into_inner().unwrap());
+	}
+
+	/// Opening position with only one move left, which leads to a draw.
+	fn drawn_opening() -> Board {
+		let mut board = Board::default();
+		let (width, height) = board.dimensions();
+		for x in 0..width {
+			let shift = usize::from(matches!(x % 4, 2 | 3));
+			for y in 0..height {
+				if (x, y) == (0, height - 1) {
+					continue;
+				}
+				let team = if (y + shift) % 2 == 0 { Team::X } else { Team::O };
+				board.put_tile(x, team).unwrap();
+			}
+		}
+		board
+	}
+
+	#[test]
+	fn draw_value_changes_league_scores() {
+		let models = [ScriptedPlayer { varied: false }, ScriptedPlayer { varied: true }];
+		let openings = Openings::new(vec![drawn_opening()], 0);
+		assert_eq!(play_match(&models[0], &models[1], drawn_opening()), GameResult::Draw);
+
+		let neutral = league_scores_with_openings(&models, &openings, &ScoringConfig::default());
+		assert_eq!(neutral, vec![0.0, 0.0]);
+
+		let scoring = ScoringConfig::builder().draw(0.5).build();
+		let positive = league_scores_with_openings(&models, &openings, &scoring);
+		assert_eq!(positive, vec![2.0, 2.0]);
+
+		let scoring = ScoringConfig::builder().draw(-0.5).normalize(true).build();
+		let negative = league_scores_with_openings(&models, &openings, &scoring);
+		assert_eq!(negative, vec![-0.5, -0.5]);
+	}
+
+	#[test]
+	fn traced_game_replays() {
+		let (player_x, player_o) =
+			(ScriptedPlayer { varied: true }, ScriptedPlayer { varied: false });
+		let record = play_traced(&player_x, &player_o);
+
+		let mut game = Game::builder().player_x(&player_x).player_o(&player_o).build();
+		let result = game.run_error_loss();
+		assert_eq!(record.result, result);
+		assert_eq!(record.moves, game.history());
+
+		let board = record.replay().unwrap();
+		assert_eq!(&board, game.board());
+		assert_eq!(board.game_result(), Some(record.result));
+		assert!(record.to_string().ends_with(&format!("Result: {result}")));
+	}
+
+	#[test]
+	fn previous_models_stay_bounded() {
+		let mut evaluator =
+			PlayerPlusEvaluator::default().with_freeze_every(3).with_max_previous(4);
+		let models = [ScriptedPlayer { varied: true }];
+
+		// Skip the expensive games and only freeze like an evaluation does.
+		for step in 1..=100 {
+			evaluator.freeze_best(&models, &[1.0]);
+			assert!(evaluator.previous_len() <= 4);
+			assert_eq!(evaluator.previous_len(), (step / 3).min(4));
+		}
+	}
+
+	#[test]
+	fn value_net_agrees_on_forced_win() {
+		let model = AiValuePlayer::<NdArrayBackend>::init(1);
+		let mut board = Board::default();
+		for column in [0, 1, 2] {
+			board.put_tile(column, Team::X).unwrap();
+			board.put_tile(column, Team::O).unwrap();
+		}
+
+		let agreement = value_net_vs_minimax_agreement(&model, 3, &[board]);
+		assert!((agreement - 1.0).abs() < f32::EPSILON);
+		assert!(value_net_vs_minimax_agreement(&model, 3, &[]).abs() < f32::EPSILON);
+	}
+
+	#[test]
+	fn move_penalty_prefers_fast_wins() {
+		/// Player always playing the same column.
+		#[derive(Debug)]
+		struct ColumnPlayer(usize);
+
+		impl Player for ColumnPlayer {
+			fn make_move(&self, _board: &Board, _me: Team) -> usize {
+				self.0
+			}
+		}
+
+		// Vertical win in column 0 after 7 moves.
+		let fast = play_traced(&ScriptedPlayer { varied: false }, &ColumnPlayer(6));
+		// Columns are filled one after the other, horizontal win after 19 moves.
+		let slow =
+			play_traced(&ScriptedPlayer { varied: false }, &ScriptedPlayer { varied: false });
+		assert_eq!(fast.result, GameResult::Winner(Team::X));
+		assert_eq!(slow.result, GameResult::Winner(Team::X));
+		assert!(fast.moves.len() < slow.moves.len());
+
+		let plain = ScoringConfig::default();
+		assert_eq!(plain.score(&fast, Team::X), plain.score(&slow, Team::X));
+
+		let penalized = ScoringConfig::builder().move_penalty(0.01).build();
+		assert!(penalized.score(&fast, Team::X) > penalized.score(&slow, Team::X));
+		assert!(penalized.score(&fast, Team::O) < penalized.score(&slow, Team::O));
+		assert!(penalized.score(&slow, Team::X) > penalized.score(&slow, Team::O));
+	}
+
+	#[test]
+	fn single_thread_pool_matches_global_pool() {
+		let models = [
+			ScriptedPlayer { varied: false },
+			ScriptedPlayer { varied: true },
+			ScriptedPlayer { varied: true },
+		];
+		let pool = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+		let mut evaluator = ThreadPoolEvaluator::new(league_scores, Arc::new(pool));
+		assert_eq!(evaluator.evaluate(&models), league_scores(&models));
+	}
+
+	#[test]
+	fn color_balance_matches_single_games() {
+		let model = ScriptedPlayer { varied: true };
+		let opponent = ScriptedPlayer { varied: false };
+		let win_rate = |result: GameResult, team: Team| {
+			if result == GameResult::Winner(team) {
+				1.0
+			} else {
+				0.0
+			}
+		};
+		let expected_x = win_rate(play_match(&model, &opponent, Board::default()), Team::X);
+		let expected_o = win_rate(play_match(&opponent, &model, Board::default()), Team::O);
+
+		assert_eq!(color_balance(&model, &opponent, 4), (expected_x, expected_o));
+		assert_eq!(color_balance(&model, &opponent, 0), (0.0, 0.0));
+
+		// Against itself, exactly one color wins each game unless it is a draw.
+		let (as_x, as_o) = color_balance(&model, &model, 2);
+		let draw = play_match(&model, &model, Board::default()) == GameResult::Draw;
+		assert_eq!(as_x + as_o, if draw { 0.0 } else { 1.0 });
+	}
+
+	#[test]
+	fn random_opponents_are_identical_across_models() {
+		/// Player recording the moves of an inner player.
+		#[derive(Debug)]
+		struct Recorder<'a>(&'a dyn Player, Mutex<Vec<usize>>);
+
+		impl Player for Recorder<'_> {
+			fn make_move(&self, board: &Board, me: Team) -> usize {
+				let column = self.0.make_move(board, me);
+				self.1.lock().unwrap().push(column);
+				column
+			}
+		}
+
+		let models = [ScriptedPlayer { varied: false }, ScriptedPlayer { varied: true }];
+		for i in 0..10 {
+			let sequences = models
+				.iter()
+				.map(|model| {
+					let opponent = random_opponent(RANDOM_OPPONENT_SEED, i);
+					let recorder = Recorder(&opponent, Mutex::new(Vec::new()));
+					Game::builder().player_x(&recorder).player_o(model).build().run_error_loss();
+					recorder.1.into_inner().unwrap()
+				})
+				.collect::<Vec<_>>();
+			// The first three random moves cannot hit a full column yet.
+			assert_eq!(sequences[0][..3], sequences[1][..3], "game {i}");
+		}
+		assert_eq!(
+			test_random_with_seed(&models[0], 20, 1),
+			test_random_with_seed(&models[0], 20, 1)
+		);
+	}
+
+	#[test]
+	fn consistency_variance() {
+		/// Player resigning at its first move with a chance of 50%, otherwise
+		/// playing like the deterministic scripted player.
+		#[derive(Debug)]
+		struct CoinFlipPlayer(Mutex<StdRng>);
+
+		impl Player for CoinFlipPlayer {
+			fn make_move(&self, board: &Board, me: Team) -> usize {
+				ScriptedPlayer { varied: false }.make_move(board, me)
+			}
+
+			fn should_resign(&self, board: &Board, _me: Team) -> bool {
+				board.field().iter().filter(|tile| tile.is_some()).count() < 2
+					&& self.0.lock().unwrap().gen_bool(0.5)
+			}
+		}
+
+		let seed = 0;
+		let evaluator =
+			ConsistencyEvaluator::new(|_seed| ScriptedPlayer { varied: false }, 100, seed);
+		let (_mean, variance) = evaluator.score_stats(&ScriptedPlayer { varied: false });
+		assert_eq!(variance, 0.0);
+		let coin_flip = CoinFlipPlayer(Mutex::new(StdRng::seed_from_u64(seed)));
+		let (_mean, variance) = evaluator.score_stats(&coin_flip);
+		assert!(variance > 0.1, "variance {variance}");
+
+		let mut evaluator = evaluator.with_variance_penalty(1.0);
+		let scores = evaluator.evaluate(&[ScriptedPlayer { varied: false }]);
+		assert_eq!(scores, vec![evaluator.score_stats(&ScriptedPlayer { varied: false }).0]);
+	}
+
+	#[test]
+	fn finds_solver_disagreements() {
+		// X wins right away in column 3 only.
+		let mut win_in_3 = Board::default();
+		for column in [0, 0, 1, 1, 2, 2] {
+			win_in_3.put_tile(column, win_in_3.whos_turn()).unwrap();
+		}
+		// X wins right away in column 0 only.
+		let mut win_in_0 = Board::default();
+		for column in [0, 1, 0, 1, 0, 6] {
+			win_in_0.put_tile(column, win_in_0.whos_turn()).unwrap();
+		}
+
+		let model = ScriptedPlayer { varied: false };
+		let disagreements =
+			solver_disagreements(&model, &[win_in_3, win_in_0], &SolverPlayer::new(4));
+		assert_eq!(disagreements, vec![(win_in_3, 0, 3)]);
+	}
+}
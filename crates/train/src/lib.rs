@@ -3,21 +3,28 @@
 
 pub mod evaluation;
 pub mod optimizers;
+pub mod progress;
+pub mod report;
+pub mod self_play;
+pub mod supervised;
 mod utils;
 
-use std::{fmt::Debug, marker::PhantomData};
+use std::{cmp::Ordering, fmt::Debug, fs::File, marker::PhantomData, path::Path, time::Instant};
 
 use burn::{
 	module::Module,
+	record::{FullPrecisionSettings, NamedMpkGzFileRecorder},
 	tensor::{backend::Backend, ElementConversion, Tensor},
 };
 use game::Player;
-use rand::{rngs::StdRng, seq::SliceRandom, thread_rng, Rng, SeedableRng};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
 use rand_distr::Distribution;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 use self::{
 	evaluation::Evaluator,
 	optimizers::Optimizer,
+	report::{StepReport, TrainingReporter},
 	utils::{FlattenVisitor, ModifyMapper, OverrideMapper},
 };
 
@@ -39,10 +46,19 @@ where
 	std: f32,
 	/// The double-sided sample/population size.
 	samples: usize,
+	/// Whether to use antithetic sampling, i.e. evaluating both the positive
+	/// and negative side of each disposition. Otherwise only the positive side
+	/// is evaluated, halving the evaluation cost at the price of a noisier
+	/// gradient estimate.
+	#[builder(default = true)]
+	antithetic: bool,
 	/// Evaluation function to compute the scores of a population.
 	evaluator: Eval,
 	/// The optimizer to use.
 	optimizer: Opt,
+	/// Random number generator for the seeds of the training steps.
+	#[builder(setter(skip), default = StdRng::from_entropy())]
+	rng: StdRng,
 }
 
 impl<B, Model, Eval, Opt> EsTrainer<B, Model, Eval, Opt>
@@ -52,6 +68,24 @@ where
 	Eval: Evaluator<Model>,
 	Opt: Optimizer<B> + Debug,
 {
+	/// Seed the random number generator of the trainer, so that a training
+	/// run with a deterministic evaluator can be reproduced.
+	#[must_use]
+	pub fn with_seed(mut self, seed: u64) -> Self {
+		self.rng = StdRng::seed_from_u64(seed);
+		self
+	}
+
+	/// Get the standard deviation used for sampling.
+	pub fn std(&self) -> f32 {
+		self.std
+	}
+
+	/// Get the double-sided sample size.
+	pub fn samples(&self) -> usize {
+		self.samples
+	}
+
 	/// Get the optimizer.
 	pub fn optimizer(&self) -> &Opt {
 		&self.optimizer
@@ -97,26 +131,40 @@ where
 		let mut population = Vec::with_capacity(self.samples * 2);
 		for i in 0..self.samples {
 			let disposition = self.generate_model_params(seed, i);
-			population.push(self.modified_model(disposition.clone()));
-			population.push(self.modified_model(disposition.mul_scalar(-1)));
+			if self.antithetic {
+				population.push(self.modified_model(disposition.clone()));
+				population.push(self.modified_model(disposition.mul_scalar(-1)));
+			} else {
+				population.push(self.modified_model(disposition));
+			}
 		}
 		population
 	}
 
 	/// Compute the gradient from the scores. Generates the same dispositions as
-	/// the population generation using the same seed.
+	/// the population generation using the same seed. Without antithetic
+	/// sampling, the scores are expected to be baseline-subtracted, which the
+	/// normalization does.
 	fn compute_gradient(&self, seed: u64, scores: &[f32]) -> Tensor<B, 1> {
 		let mut gradient = Tensor::zeros([self.model.num_params()]);
 		for i in 0..self.samples {
 			let disposition = self.generate_model_params(seed, i);
-			gradient = gradient + disposition.mul_scalar(scores[i * 2] - scores[i * 2 + 1]);
+			let score = if self.antithetic { scores[i * 2] - scores[i * 2 + 1] } else { scores[i] };
+			gradient = gradient + disposition.mul_scalar(score);
 		}
-		gradient.mul_scalar(1.0 / (2.0 * self.samples as f32 * self.std))
+		let sides = if self.antithetic { 2.0 } else { 1.0 };
+		gradient.mul_scalar(1.0 / (sides * self.samples as f32 * self.std))
 	}
 
 	/// Train the model for one step.
 	pub fn train_step(&mut self) -> &mut Self {
-		let seed = rand::random();
+		let seed = self.rng.gen();
+		self.train_step_with_seed(seed)
+	}
+
+	/// Train the model for one step, using the seed to generate the
+	/// population. Training is reproducible with deterministic evaluators.
+	pub fn train_step_with_seed(&mut self, seed: u64) -> &mut Self {
 		let population = time!(self.generate_population(seed), "Generating population");
 		let mut scores = time!(self.evaluator.evaluate(&population), "Computing population scores");
 		normalize_scores(&mut scores);
@@ -128,6 +176,77 @@ where
 	}
 }
 
+/// Scalar hyperparameters of the [`EsTrainer`], saved in checkpoints.
+#[derive(Debug, Serialize, Deserialize)]
+struct EsHyperparameters {
+	/// Standard deviation to use for sampling while training.
+	std: f32,
+	/// The double-sided sample/population size.
+	samples: usize,
+	/// Whether to use antithetic sampling.
+	#[serde(default = "default_antithetic")]
+	antithetic: bool,
+}
+
+/// Default of [`EsHyperparameters::antithetic`], for checkpoints written
+/// before the option existed.
+const fn default_antithetic() -> bool {
+	true
+}
+
+impl<B, Model, Eval, Opt> EsTrainer<B, Model, Eval, Opt>
+where
+	B: Backend + Debug,
+	Model: Module<B> + Player + Debug,
+	Eval: Evaluator<Model>,
+	Opt: Optimizer<B> + Debug + Serialize + DeserializeOwned,
+{
+	/// Save the full trainer state to the directory: the model, the optimizer
+	/// and the hyperparameters. The evaluator is not saved.
+	pub fn save_checkpoint(&self, dir: impl AsRef<Path>) -> Result<(), Box<dyn std::error::Error>> {
+		let dir = dir.as_ref();
+		std::fs::create_dir_all(dir)?;
+		self.model.clone().save_file(
+			dir.join("model"),
+			&NamedMpkGzFileRecorder::<FullPrecisionSettings>::new(),
+		)?;
+		serde_json::to_writer(File::create(dir.join("optimizer.json"))?, &self.optimizer)?;
+		let hyperparameters =
+			EsHyperparameters { std: self.std, samples: self.samples, antithetic: self.antithetic };
+		serde_json::to_writer(File::create(dir.join("hyperparameters.json"))?, &hyperparameters)?;
+		Ok(())
+	}
+
+	/// Load the full trainer state from a checkpoint directory written by
+	/// [`EsTrainer::save_checkpoint`]. The model parameters are loaded into the
+	/// given model, which needs to have the same architecture. The evaluator is
+	/// not part of the checkpoint, so it is supplied again.
+	pub fn load_checkpoint(
+		dir: impl AsRef<Path>,
+		model: Model,
+		evaluator: Eval,
+	) -> Result<Self, Box<dyn std::error::Error>> {
+		let dir = dir.as_ref();
+		let model = model.load_file(
+			dir.join("model"),
+			&NamedMpkGzFileRecorder::<FullPrecisionSettings>::new(),
+		)?;
+		let optimizer = serde_json::from_reader(File::open(dir.join("optimizer.json"))?)?;
+		let EsHyperparameters { std, samples, antithetic } =
+			serde_json::from_reader(File::open(dir.join("hyperparameters.json"))?)?;
+		Ok(Self {
+			backend: PhantomData,
+			model,
+			std,
+			samples,
+			antithetic,
+			evaluator,
+			optimizer,
+			rng: StdRng::from_entropy(),
+		})
+	}
+}
+
 /// Normalize a vec of floats.
 fn normalize_scores(scores: &mut [f32]) {
 	let mut mean = 0.0;
@@ -149,6 +268,20 @@ fn normalize_scores(scores: &mut [f32]) {
 	}
 }
 
+/// Sort models by their scores, best first. The sort is stable, so models with
+/// equal scores keep their order in the population, i.e. older survivors come
+/// before newly generated models. NaN scores are sorted to the bottom.
+fn sort_by_score<Model>(models: impl IntoIterator<Item = Model>, scores: Vec<f32>) -> Vec<Model> {
+	let mut population_scores = models.into_iter().zip(scores).collect::<Vec<_>>();
+	population_scores.sort_by(|(_, a), (_, b)| match (a.is_nan(), b.is_nan()) {
+		(true, true) => Ordering::Equal,
+		(true, false) => Ordering::Greater,
+		(false, true) => Ordering::Less,
+		(false, false) => b.partial_cmp(a).unwrap_or(Ordering::Equal),
+	});
+	population_scores.into_iter().map(|(model, _score)| model).collect()
+}
+
 /// The model trainer using pure evolution with breeding, mutation and
 /// selection.
 #[derive(typed_builder::TypedBuilder)]
@@ -175,8 +308,29 @@ where
 	mutation_probability: f64,
 	/// Mutation range standard deviation.
 	mutation_std: f64,
+	/// L2 distance of the model parameters, below which models are considered
+	/// duplicates when merging populations.
+	#[builder(default = 1e-6)]
+	duplicate_distance: f32,
 	/// Evaluation function to compute the scores of a population.
 	evaluator: Eval,
+	/// Random number generator for breeding and selection.
+	#[builder(setter(skip), default = StdRng::from_entropy())]
+	rng: StdRng,
+	/// Optional reporter of the metrics of each training step.
+	#[builder(default)]
+	reporter: Option<Box<dyn TrainingReporter>>,
+	/// Optional validation evaluator, scoring the best model against opponents
+	/// not used for selection to detect overfitting. Its scores are only
+	/// reported, never used for selection.
+	#[builder(default)]
+	validator: Option<Box<dyn Evaluator<Model>>>,
+	/// Run the validation every this many training steps.
+	#[builder(default = 1)]
+	validation_interval: usize,
+	/// Number of training steps done so far.
+	#[builder(setter(skip), default)]
+	step: usize,
 }
 
 impl<B, Model, Eval> EvolutionTrainer<B, Model, Eval>
@@ -185,6 +339,20 @@ where
 	Model: Module<B> + Player + Debug,
 	Eval: Evaluator<Model>,
 {
+	/// Seed the random number generators of the trainer. This also seeds the
+	/// backend, which generates the random tensors for breeding and mutation.
+	#[must_use]
+	pub fn with_seed(mut self, seed: u64) -> Self {
+		self.rng = StdRng::seed_from_u64(seed);
+		B::seed(seed);
+		self
+	}
+
+	/// Get the population size limits as `(min, max)`.
+	pub fn population_limits(&self) -> (usize, usize) {
+		(self.population_min, self.population_max)
+	}
+
 	/// Get the population.
 	pub fn population(&self) -> &[Model] {
 		&self.population
@@ -200,14 +368,17 @@ where
 		&mut self.evaluator
 	}
 
+	/// Get the flat parameters of a model.
+	fn flatten(model: &Model) -> Tensor<B, 1> {
+		let mut visitor = FlattenVisitor { parameters: None };
+		model.visit(&mut visitor);
+		visitor.parameters.expect("Model should not be empty")
+	}
+
 	/// Breed a new model from 2 parent models.
 	pub fn breed(a: &Model, b: &Model) -> Model {
-		let mut visitor_a = FlattenVisitor { parameters: None };
-		a.visit(&mut visitor_a);
-		let params_a = visitor_a.parameters.expect("Model should not be empty");
-		let mut visitor_b = FlattenVisitor { parameters: None };
-		b.visit(&mut visitor_b);
-		let params_b = visitor_b.parameters.expect("Model should not be empty");
+		let params_a = Self::flatten(a);
+		let params_b = Self::flatten(b);
 
 		let mask = Tensor::random(
 			[a.num_params()],
@@ -235,19 +406,18 @@ where
 
 	/// Generate population via breeding and mutation.
 	pub fn generate_population(&mut self) {
-		let mut rng = thread_rng();
-
 		while self.population.len() < self.population_min {
 			self.population.push((self.init_fn)());
 		}
 
 		while self.population.len() < self.population_max {
-			if rng.gen::<f64>() < self.generate_new {
+			if self.rng.gen::<f64>() < self.generate_new {
 				self.population.push((self.init_fn)());
 			} else {
-				let selected = self.population.choose_multiple(&mut rng, 2).collect::<Vec<_>>();
+				let selected =
+					self.population.choose_multiple(&mut self.rng, 2).collect::<Vec<_>>();
 				let mut model = Self::breed(selected[0], selected[1]);
-				if rng.gen::<f64>() < self.mutation_probability {
+				if self.rng.gen::<f64>() < self.mutation_probability {
 					model = self.mutate(model);
 				}
 				self.population.push(model);
@@ -255,19 +425,97 @@ where
 		}
 	}
 
+	/// Compute the diversity of the population, the mean L2 distance of the
+	/// model parameters to the mean parameters.
+	fn diversity(population: &[Model]) -> f32 {
+		let parameters = population.iter().map(Self::flatten).collect::<Vec<_>>();
+		let Some(sum) = parameters.iter().cloned().reduce(|a, b| a + b) else {
+			return 0.0;
+		};
+		let mean = sum.div_scalar(parameters.len() as f32);
+		let distances = parameters.into_iter().map(|parameters| {
+			let diff = parameters - mean.clone();
+			let distance: f32 = (diff.clone() * diff).sum().into_scalar().elem();
+			distance.sqrt()
+		});
+		distances.sum::<f32>() / population.len() as f32
+	}
+
+	/// Score the best model with the validation evaluator, if there is one
+	/// and it is a validation step. Must be called after selection, when the
+	/// best model is first in the population.
+	fn validate(&mut self) -> Option<f32> {
+		let validator = self.validator.as_mut()?;
+		if !self.step.is_multiple_of(self.validation_interval.max(1)) {
+			return None;
+		}
+		let best = self.population.get(..1)?;
+		time!(validator.evaluate(best), "Computing validation score").first().copied()
+	}
+
 	/// Train for one step.
 	pub fn train_step(&mut self) -> &mut Self {
+		let start = Instant::now();
 		time!(self.generate_population(), "Generating population");
 		let scores =
 			time!(self.evaluator.evaluate(&self.population), "Computing population scores");
+		let best_score = scores.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+		let mean_score = scores.iter().sum::<f32>() / scores.len() as f32;
 
 		// Sort population by scores and select the best.
-		let mut population_scores = self.population.drain(..).zip(scores).collect::<Vec<_>>();
-		population_scores
-			.sort_unstable_by(|(_, a), (_, b)| b.partial_cmp(a).expect("Score was NaN"));
-		self.population.append(
-			&mut population_scores.into_iter().take(self.population_min).map(|(m, _s)| m).collect(),
-		);
+		let population = sort_by_score(self.population.drain(..), scores);
+		self.population.extend(population.into_iter().take(self.population_min));
+		let validation_score = self.validate();
+
+		if let Some(reporter) = &mut self.reporter {
+			reporter.on_step(&StepReport {
+				step: self.step,
+				best_score,
+				mean_score,
+				validation_score,
+				diversity: Self::diversity(&self.population),
+				elapsed: start.elapsed(),
+			});
+		}
+		self.step += 1;
+
+		self
+	}
+}
+
+impl<B, Model, Eval> EvolutionTrainer<B, Model, Eval>
+where
+	B: Backend + Debug,
+	Model: Module<B> + Player + Debug,
+	Eval: Evaluator<Model>,
+{
+	/// Merge other models (e.g. the population of another training run) into
+	/// the population. All models are scored together and the best `keep`
+	/// models are kept, dropping models that are near-identical to an already
+	/// kept better model.
+	pub fn merge_population(&mut self, others: Vec<Model>, keep: usize) -> &mut Self {
+		self.population.extend(others);
+		let scores = self.evaluator.evaluate(&self.population);
+
+		let population = sort_by_score(self.population.drain(..), scores);
+
+		let mut kept: Vec<(Model, Tensor<B, 1>)> = Vec::with_capacity(keep);
+		for model in population {
+			if kept.len() >= keep {
+				break;
+			}
+
+			let parameters = Self::flatten(&model);
+			let duplicate = kept.iter().any(|(_, kept_parameters)| {
+				let diff = parameters.clone() - kept_parameters.clone();
+				let distance: f32 = (diff.clone() * diff).sum().into_scalar().elem();
+				distance.sqrt() < self.duplicate_distance
+			});
+			if !duplicate {
+				kept.push((model, parameters));
+			}
+		}
+		self.population = kept.into_iter().map(|(model, _)| model).collect();
 
 		self
 	}
@@ -289,7 +537,173 @@ where
 			.field("generate_new", &self.generate_new)
 			.field("mutation_probability", &self.mutation_probability)
 			.field("mutation_std", &self.mutation_std)
+			.field("duplicate_distance", &self.duplicate_distance)
 			.field("evaluator", &self.evaluator)
 			.finish()
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use players::{AiValuePlayer, NdArrayBackend};
+
+	use super::*;
+	use crate::optimizers::Sgd;
+
+	/// Model type used in the tests.
+	type TestModel = AiValuePlayer<NdArrayBackend>;
+
+	/// Deterministic evaluator, scoring the models by their position.
+	fn index_scores(models: &[TestModel]) -> Vec<f32> {
+		(0..models.len()).map(|i| i as f32).collect()
+	}
+
+	#[test]
+	fn merging_clones_does_not_add_diversity() {
+		let population = (0..3).map(|_| TestModel::init(1)).collect::<Vec<_>>();
+		let clones = population.clone();
+		let mut trainer = EvolutionTrainer::builder()
+			.population(population)
+			.init_fn(Box::new(|| TestModel::init(1)))
+			.evaluator(index_scores)
+			.population_max(10)
+			.population_min(3)
+			.generate_new(0.0)
+			.mutation_probability(0.0)
+			.mutation_std(0.0)
+			.build();
+
+		trainer.merge_population(clones, 5);
+		assert_eq!(trainer.population().len(), 3);
+
+		trainer.merge_population(vec![TestModel::init(1)], 5);
+		assert_eq!(trainer.population().len(), 4);
+	}
+
+	#[test]
+	fn equal_scores_keep_population_order() {
+		let sorted = sort_by_score([0, 1, 2, 3, 4], vec![1.0, 2.0, 1.0, 2.0, 3.0]);
+		assert_eq!(sorted, [4, 1, 3, 0, 2]);
+	}
+
+	#[test]
+	fn nan_scores_sink_to_the_bottom() {
+		let sorted = sort_by_score([0, 1, 2, 3], vec![f32::NAN, 1.0, f32::NAN, 0.0]);
+		assert_eq!(sorted, [1, 3, 0, 2]);
+
+		let mut trainer = EvolutionTrainer::builder()
+			.population(Vec::new())
+			.init_fn(Box::new(|| TestModel::init(1)))
+			.evaluator(|models: &[TestModel]| vec![f32::NAN; models.len()])
+			.population_max(4)
+			.population_min(2)
+			.generate_new(1.0)
+			.mutation_probability(0.0)
+			.mutation_std(0.0)
+			.build();
+		trainer.train_step();
+		assert_eq!(trainer.population().len(), 2);
+	}
+
+	/// Reporter collecting the reports.
+	#[derive(Debug, Default, Clone)]
+	struct CollectingReporter(std::rc::Rc<std::cell::RefCell<Vec<StepReport>>>);
+
+	impl TrainingReporter for CollectingReporter {
+		fn on_step(&mut self, report: &StepReport) {
+			self.0.borrow_mut().push(*report);
+		}
+	}
+
+	#[test]
+	fn validation_is_reported_but_not_selected_on() {
+		let reports = CollectingReporter::default();
+		let validated = std::rc::Rc::new(std::cell::Cell::new(0));
+		let validated_models = validated.clone();
+		let mut trainer = EvolutionTrainer::builder()
+			.population(Vec::new())
+			.init_fn(Box::new(|| TestModel::init(1)))
+			.evaluator(index_scores)
+			.population_max(4)
+			.population_min(2)
+			.generate_new(1.0)
+			.mutation_probability(0.0)
+			.mutation_std(0.0)
+			.reporter(Some(Box::new(reports.clone())))
+			.validator(Some(Box::new(move |models: &[TestModel]| {
+				validated_models.set(validated_models.get() + models.len());
+				vec![-100.0; models.len()]
+			})))
+			.validation_interval(2)
+			.build();
+		for _ in 0..3 {
+			trainer.train_step();
+		}
+
+		assert_eq!(validated.get(), 2);
+		let reports = reports.0.borrow();
+		let validation_scores =
+			reports.iter().map(|report| report.validation_score).collect::<Vec<_>>();
+		assert_eq!(validation_scores, [Some(-100.0), None, Some(-100.0)]);
+		for report in reports.iter() {
+			assert!((report.best_score - 3.0).abs() < f32::EPSILON);
+			assert!((report.mean_score - 1.5).abs() < f32::EPSILON);
+		}
+	}
+
+	#[test]
+	fn forward_only_evaluates_half_the_models() {
+		let evaluated = |antithetic: bool| {
+			let mut count = 0;
+			let optimizer =
+				Sgd::<NdArrayBackend>::builder().learning_rate(0.1).momentum(0.9).build();
+			let mut trainer = EsTrainer::builder()
+				.model(TestModel::init(1))
+				.evaluator(|models: &[TestModel]| {
+					count += models.len();
+					index_scores(models)
+				})
+				.optimizer(optimizer)
+				.samples(4)
+				.std(0.1)
+				.antithetic(antithetic)
+				.build();
+			trainer.train_step_with_seed(1);
+			drop(trainer);
+			count
+		};
+
+		assert_eq!(evaluated(true), 8);
+		assert_eq!(evaluated(false), 4);
+	}
+
+	#[test]
+	fn es_checkpoint_round_trip() {
+		let dir = std::env::temp_dir().join("connect-four-es-checkpoint-test");
+		let optimizer = Sgd::<NdArrayBackend>::builder().learning_rate(0.1).momentum(0.9).build();
+		let mut trainer = EsTrainer::builder()
+			.model(TestModel::init(1))
+			.evaluator(index_scores)
+			.optimizer(optimizer)
+			.samples(2)
+			.std(0.1)
+			.build();
+		trainer.train_step_with_seed(1);
+
+		trainer.save_checkpoint(&dir).unwrap();
+		let mut loaded: EsTrainer<_, _, _, Sgd<NdArrayBackend>> =
+			EsTrainer::load_checkpoint(&dir, TestModel::init(1), index_scores).unwrap();
+		std::fs::remove_dir_all(&dir).unwrap();
+
+		trainer.train_step_with_seed(2);
+		loaded.train_step_with_seed(2);
+		let parameters = |model: &TestModel| {
+			let mut visitor = FlattenVisitor { parameters: None };
+			model.visit(&mut visitor);
+			visitor.parameters.unwrap().into_data().value
+		};
+		assert_eq!(parameters(trainer.model()), parameters(loaded.model()));
+	}
+}
@@ -1,45 +1,223 @@
 //! Execute training of the connect four AI.
 #![allow(clippy::print_stdout, clippy::expect_used)]
 
-use std::path::Path;
+use std::{
+	fs::File,
+	path::{Path, PathBuf},
+};
 
 use burn::tensor::backend::Backend;
+use clap::{Args, Parser, Subcommand};
 use players::{AiValuePlayer, NdArrayBackend};
-use train::{evaluation::*, optimizers::*, time, EsTrainer, EvolutionTrainer};
+use serde::{Deserialize, Serialize};
+use train::{
+	evaluation::*,
+	optimizers::*,
+	progress::ChampionHistory,
+	report::{CsvLogger, TrainingReporter},
+	time, EsTrainer, EvolutionTrainer,
+};
+
+/// Model type that is trained.
+type Model = AiValuePlayer<NdArrayBackend>;
+/// Evaluator used for training.
+type Eval = fn(&[Model]) -> Vec<f32>;
+
+/// Train the connect four AI.
+#[derive(Debug, Parser)]
+struct Cli {
+	/// Folder to load the models from and save them to.
+	#[arg(long, default_value = "./models")]
+	model_path: PathBuf,
+	/// Master seed for all random number generators of the training run. A
+	/// random seed is used if none is given.
+	#[arg(long)]
+	seed: Option<u64>,
+	/// Number of training steps.
+	#[arg(long, default_value_t = 10000)]
+	steps: usize,
+	/// CSV file to append the metrics of each evolution training step to.
+	#[arg(long)]
+	metrics: Option<PathBuf>,
+	/// Training method.
+	#[command(subcommand)]
+	method: Method,
+}
+
+/// Training method with its hyperparameters.
+#[derive(Debug, Clone, PartialEq, Subcommand, Serialize, Deserialize)]
+enum Method {
+	/// Train using evolution strategies.
+	Es(EsConfig),
+	/// Train using evolution.
+	Evo(EvoConfig),
+}
+
+/// Hyperparameters of the evolution strategies training.
+#[derive(Debug, Clone, PartialEq, Args, Serialize, Deserialize)]
+struct EsConfig {
+	/// File to load the optimizer from and save it to.
+	#[arg(long, default_value = "./optimizer.json")]
+	optimizer_path: PathBuf,
+	/// The double-sided sample/population size.
+	#[arg(long, default_value_t = 100)]
+	samples: usize,
+	/// Standard deviation to use for sampling.
+	#[arg(long, default_value_t = 0.02)]
+	std: f32,
+	/// Evaluate only the positive side of each sample instead of both sides.
+	#[arg(long)]
+	forward_only: bool,
+	/// Learning rate of a new optimizer.
+	#[arg(long, default_value_t = 0.025)]
+	learning_rate: f32,
+	/// Momentum of a new optimizer.
+	#[arg(long, default_value_t = 0.9)]
+	momentum: f32,
+}
+
+/// Hyperparameters of the evolution training.
+#[derive(Debug, Clone, PartialEq, Args, Serialize, Deserialize)]
+struct EvoConfig {
+	/// Maximum population size to generate.
+	#[arg(long, default_value_t = 200)]
+	population_max: usize,
+	/// Minimum population size to select.
+	#[arg(long, default_value_t = 20)]
+	population_min: usize,
+	/// Probability to generate a new model.
+	#[arg(long, default_value_t = 0.01)]
+	generate_new: f64,
+	/// Probability of mutation.
+	#[arg(long, default_value_t = 0.1)]
+	mutation_probability: f64,
+	/// Mutation range standard deviation.
+	#[arg(long, default_value_t = 0.005)]
+	mutation_std: f64,
+}
+
+/// Manifest of a saved population, written alongside the models.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Manifest {
+	/// Number of saved models.
+	count: usize,
+	/// Index of the best model.
+	best: usize,
+	/// Metadata of the training run that produced the models.
+	run: RunMetadata,
+}
+
+/// Metadata of a training run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct RunMetadata {
+	/// Master seed of the run.
+	seed: u64,
+	/// Training step at which the models were saved.
+	step: usize,
+	/// Training method and hyperparameters.
+	method: Method,
+}
+
+/// Number of steps to look back for the champion to compare against.
+const CHAMPION_LOOKBACK: usize = 10;
+
+/// File name of the population manifest.
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Number of evolution training steps between validations of the best model.
+const VALIDATION_INTERVAL: usize = 10;
+
+/// Seed of the random opponents in validation, distinct from the ones used for
+/// selection.
+const VALIDATION_SEED: u64 = 0x0BAD_5EED;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-	main_evo()
+	let cli = Cli::parse();
+	let seed = cli.seed.unwrap_or_else(rand::random);
+	println!("Training with seed {seed}");
+
+	match &cli.method {
+		Method::Es(config) => main_es(&cli, config, seed),
+		Method::Evo(config) => main_evo(&cli, config, seed),
+	}
 }
 
-/// Run training using evolution strategies.
-#[allow(dead_code)]
-fn main_es() -> Result<(), Box<dyn std::error::Error>> {
-	let model_path = "./models";
-	let optimizer_path = "./optimizer.json";
+/// Build the evolution strategies trainer from the configuration.
+fn build_es_trainer(
+	config: &EsConfig,
+	seed: u64,
+	model: Model,
+	optimizer: Option<Sgd<NdArrayBackend>>,
+) -> EsTrainer<NdArrayBackend, Model, Eval, Sgd<NdArrayBackend>> {
+	let optimizer = optimizer.unwrap_or_else(|| {
+		Sgd::builder().learning_rate(config.learning_rate).momentum(config.momentum).build()
+	});
+	EsTrainer::builder()
+		.model(model)
+		.evaluator(player_scores as Eval)
+		.optimizer(optimizer)
+		.samples(config.samples)
+		.std(config.std)
+		.antithetic(!config.forward_only)
+		.build()
+		.with_seed(seed)
+}
+
+/// Build the evolution trainer from the configuration.
+fn build_evolution_trainer(
+	config: &EvoConfig,
+	seed: u64,
+	population: Vec<Model>,
+	reporter: Option<Box<dyn TrainingReporter>>,
+) -> EvolutionTrainer<NdArrayBackend, Model, Eval> {
+	EvolutionTrainer::builder()
+		.population(population)
+		.init_fn(Box::new(|| AiValuePlayer::init(1)))
+		.evaluator(player_scores as Eval)
+		.population_max(config.population_max)
+		.population_min(config.population_min)
+		.generate_new(config.generate_new)
+		.mutation_probability(config.mutation_probability)
+		.mutation_std(config.mutation_std)
+		.reporter(reporter)
+		.validator(Some(Box::new(validation_scores as Eval)))
+		.validation_interval(VALIDATION_INTERVAL)
+		.build()
+		.with_seed(seed)
+}
 
-	let mut models = load_all::<NdArrayBackend>(model_path);
+/// Validation evaluation of the models against opponents not used for
+/// selection in [`player_scores`]: differently seeded random players and a
+/// shallower minimax player.
+fn validation_scores(models: &[Model]) -> Vec<f32> {
+	models
+		.iter()
+		.map(|model| {
+			test_random_with_seed(model, 1000, VALIDATION_SEED) + test_minimax_depth(model, 3)
+		})
+		.collect()
+}
+
+/// Run training using evolution strategies.
+fn main_es(cli: &Cli, config: &EsConfig, seed: u64) -> Result<(), Box<dyn std::error::Error>> {
+	let (mut models, best) = load_all::<NdArrayBackend>(&cli.model_path);
 	let model = if models.is_empty() {
 		println!("Starting with new model");
 		AiValuePlayer::init(1)
 	} else {
-		models.swap_remove(0)
+		models.swap_remove(best)
 	};
 
-	let optimizer = Sgd::load(optimizer_path).unwrap_or_else(|err| {
-		println!("Failed loading optimizer: {err}");
-		println!("Starting with new optimizer");
-		Sgd::builder().learning_rate(0.025).momentum(0.9).build()
-	});
+	let optimizer = Sgd::load(&config.optimizer_path)
+		.map_err(|err| {
+			println!("Failed loading optimizer: {err}");
+			println!("Starting with new optimizer");
+		})
+		.ok();
 
-	let mut trainer = EsTrainer::builder()
-		.model(model)
-		.evaluator(player_scores)
-		.optimizer(optimizer)
-		.samples(100)
-		.std(0.02)
-		.build();
+	let mut trainer = build_es_trainer(config, seed, model, optimizer);
 
-	for i in 0..10000 {
+	for i in 0..cli.steps {
 		time!(trainer.train_step(), "One training step");
 
 		let score = time!(test_random::<_, 1000>(trainer.model()), "Testing performance");
@@ -48,9 +226,10 @@ fn main_es() -> Result<(), Box<dyn std::error::Error>> {
 		println!("Minimax performance: {score:.2}");
 
 		if i % 5 == 0 {
-			save_all(model_path, &[trainer.model().clone()]);
+			let run = RunMetadata { seed, step: i, method: cli.method.clone() };
+			save_all(&cli.model_path, &[trainer.model().clone()], 0, run);
 			let optimizer = trainer.optimizer();
-			optimizer.save(optimizer_path)?;
+			optimizer.save(&config.optimizer_path)?;
 			println!("Models saved!");
 		}
 
@@ -61,32 +240,30 @@ fn main_es() -> Result<(), Box<dyn std::error::Error>> {
 }
 
 /// Run training using evolution.
-#[allow(dead_code)]
-fn main_evo() -> Result<(), Box<dyn std::error::Error>> {
-	let model_path = "./models";
-	let population = load_all::<NdArrayBackend>(model_path);
+fn main_evo(cli: &Cli, config: &EvoConfig, seed: u64) -> Result<(), Box<dyn std::error::Error>> {
+	let (population, _best) = load_all::<NdArrayBackend>(&cli.model_path);
+	let reporter = match &cli.metrics {
+		Some(path) => Some(Box::new(CsvLogger::create(path)?) as Box<dyn TrainingReporter>),
+		None => None,
+	};
+	let mut trainer = build_evolution_trainer(config, seed, population, reporter);
+	let mut champions = ChampionHistory::new(CHAMPION_LOOKBACK, 1);
 
-	let mut trainer = EvolutionTrainer::builder()
-		.population(population)
-		.init_fn(Box::new(|| AiValuePlayer::init(1)))
-		.evaluator(player_scores)
-		.population_max(200)
-		.population_min(20)
-		.generate_new(0.01)
-		.mutation_probability(0.1)
-		.mutation_std(0.005)
-		.build();
-
-	for i in 0..10000 {
+	for i in 0..cli.steps {
 		time!(trainer.train_step(), "One training step");
 
 		let score = time!(test_random::<_, 1000>(&trainer.population()[0]), "Testing performance");
 		println!("Random performance: {score:.3}");
 		let score = test_minimax::<_, 5>(&trainer.population()[0]);
 		println!("Minimax performance: {score:.2}");
+		if let Some(win_rate) = champions.push(trainer.population()[0].clone()) {
+			println!("Win rate against champion {CHAMPION_LOOKBACK} steps ago: {win_rate:.2}");
+		}
 
 		if i % 5 == 0 {
-			save_all(model_path, trainer.population());
+			// The population is sorted by score after each step.
+			let run = RunMetadata { seed, step: i, method: cli.method.clone() };
+			save_all(&cli.model_path, trainer.population(), 0, run);
 			println!("Models saved!");
 		}
 
@@ -96,37 +273,110 @@ fn main_evo() -> Result<(), Box<dyn std::error::Error>> {
 	Ok(())
 }
 
-/// Load all models numbered by index from the given folder.
-fn load_all<B>(folder: impl AsRef<Path>) -> Vec<AiValuePlayer<B>>
+/// Load all models numbered by index from the given folder, together with the
+/// index of the best model. The models listed in the manifest are loaded if
+/// there is one, otherwise all model files in the folder are loaded in
+/// arbitrary order, assuming the first to be the best.
+fn load_all<B>(folder: impl AsRef<Path>) -> (Vec<AiValuePlayer<B>>, usize)
 where
 	B: Backend,
 {
-	let Ok(entries) = folder.as_ref().read_dir() else {
-		return Vec::new();
+	let folder = folder.as_ref();
+	if let Ok(file) = File::open(folder.join(MANIFEST_FILE)) {
+		let manifest: Manifest = serde_json::from_reader(file).expect("reading manifest");
+		let models = (0..manifest.count)
+			.map(|i| {
+				let file = folder.join(format!("model_{i:02}"));
+				AiValuePlayer::init(1).load(file).expect("loading model")
+			})
+			.collect();
+		return (models, manifest.best);
+	}
+
+	let Ok(entries) = folder.read_dir() else {
+		return (Vec::new(), 0);
 	};
 
 	let mut models = Vec::new();
 	for entry in entries {
 		let entry = entry.expect("read directory entry");
-		if entry.path().is_file() {
-			let file = folder.as_ref().join(entry.path().file_stem().expect("model file name"));
+		let is_model = entry.file_name().to_string_lossy().starts_with("model_");
+		if entry.path().is_file() && is_model {
+			let file = folder.join(entry.path().file_stem().expect("model file name"));
 			let model = AiValuePlayer::init(1).load(file).expect("loading model");
 			models.push(model);
 		}
 	}
-	models
+	(models, 0)
 }
 
-/// Save all models numbered by index to the given folder.
-fn save_all<B>(folder: impl AsRef<Path>, models: &[AiValuePlayer<B>])
+/// Save all models numbered by index to the given folder, together with a
+/// manifest listing them, the index of the best model and the run metadata.
+fn save_all<B>(folder: impl AsRef<Path>, models: &[AiValuePlayer<B>], best: usize, run: RunMetadata)
 where
 	B: Backend,
 {
-	if !folder.as_ref().exists() {
-		std::fs::create_dir_all(folder.as_ref()).expect("creating directory");
+	let folder = folder.as_ref();
+	if !folder.exists() {
+		std::fs::create_dir_all(folder).expect("creating directory");
 	}
 	for (i, model) in models.iter().enumerate() {
-		let file = folder.as_ref().join(format!("model_{i:02}"));
+		let file = folder.join(format!("model_{i:02}"));
 		model.clone().save(file).expect("saving model");
 	}
+
+	let manifest = Manifest { count: models.len(), best, run };
+	let file = File::create(folder.join(MANIFEST_FILE)).expect("creating manifest");
+	serde_json::to_writer_pretty(file, &manifest).expect("writing manifest");
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used, clippy::float_cmp)]
+
+	use game::{Board, Player, Team};
+
+	use super::*;
+
+	#[test]
+	fn builds_trainers_from_config() {
+		let cli =
+			Cli::try_parse_from(["train", "--seed", "42", "es", "--samples", "10", "--std", "0.1"])
+				.unwrap();
+		assert_eq!(cli.seed, Some(42));
+		let Method::Es(config) = &cli.method else { panic!("expected es method") };
+		let trainer = build_es_trainer(config, 42, AiValuePlayer::init(1), None);
+		assert_eq!(trainer.samples(), 10);
+		assert_eq!(trainer.std(), 0.1);
+
+		let cli = Cli::try_parse_from(["train", "evo", "--population-min", "3"]).unwrap();
+		let Method::Evo(config) = &cli.method else { panic!("expected evo method") };
+		let trainer = build_evolution_trainer(config, 42, Vec::new(), None);
+		assert_eq!(trainer.population_limits(), (3, 200));
+	}
+
+	#[test]
+	fn population_manifest_round_trip() {
+		let dir = std::env::temp_dir().join("connect-four-manifest-test");
+		let models: Vec<Model> =
+			vec![AiValuePlayer::init(1), AiValuePlayer::init(1), AiValuePlayer::init(1)];
+		let cli = Cli::try_parse_from(["train", "evo"]).unwrap();
+		let run = RunMetadata { seed: 3, step: 10, method: cli.method };
+		save_all(&dir, &models, 2, run.clone());
+		// Stale model from an earlier, bigger population.
+		AiValuePlayer::<NdArrayBackend>::init(1).save(dir.join("model_03")).unwrap();
+
+		let (loaded, best) = load_all::<NdArrayBackend>(&dir);
+		let manifest: Manifest =
+			serde_json::from_reader(File::open(dir.join(MANIFEST_FILE)).unwrap()).unwrap();
+		std::fs::remove_dir_all(&dir).unwrap();
+
+		assert_eq!(best, 2);
+		assert_eq!(manifest, Manifest { count: 3, best: 2, run });
+		assert_eq!(loaded.len(), models.len());
+		let board = Board::default();
+		for (model, loaded) in models.iter().zip(&loaded) {
+			assert_eq!(model.scored_moves(&board, Team::X), loaded.scored_moves(&board, Team::X));
+		}
+	}
 }
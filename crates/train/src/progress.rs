@@ -0,0 +1,104 @@
+//! Tracking of the training progress.
+
+use std::collections::VecDeque;
+
+use game::{Board, GameResult, Player, Team};
+
+use crate::evaluation::play_match;
+
+/// History of the champions, i.e. the best models, of the last training steps.
+/// Each new champion is matched against the champion from a fixed number of
+/// steps before. A win rate stuck around 0.5 signals stagnation.
+#[derive(Debug, Clone)]
+pub struct ChampionHistory<Model> {
+	/// Ring buffer of the last champions, oldest first.
+	champions: VecDeque<Model>,
+	/// Number of steps to look back.
+	lookback: usize,
+	/// Number of games to play per color.
+	games: usize,
+}
+
+impl<Model: Player> ChampionHistory<Model> {
+	/// Create a new champion history comparing against the champion from
+	/// `lookback` steps ago, playing the given number of games per color.
+	#[must_use]
+	pub fn new(lookback: usize, games: usize) -> Self {
+		Self { champions: VecDeque::with_capacity(lookback + 1), lookback, games }
+	}
+
+	/// Add the champion of the current step. Returns its win rate against the
+	/// champion from `lookback` steps ago, counting draws as half a win, or
+	/// `None` if there is no champion that old yet.
+	pub fn push(&mut self, champion: Model) -> Option<f32> {
+		self.champions.push_back(champion);
+		if self.champions.len() <= self.lookback {
+			return None;
+		}
+
+		let old = self.champions.pop_front()?;
+		let current = self.champions.back()?;
+		Some(win_rate(current, &old, self.games))
+	}
+}
+
+/// Win rate of `model` against `opponent`, playing the given number of games
+/// per color. Draws count as half a win.
+fn win_rate(model: &impl Player, opponent: &impl Player, games: usize) -> f32 {
+	if games == 0 {
+		return 0.5;
+	}
+
+	let mut score = 0.0;
+	for _ in 0..games {
+		for (team, result) in [
+			(Team::X, play_match(model, opponent, Board::default())),
+			(Team::O, play_match(opponent, model, Board::default())),
+		] {
+			score += match result {
+				GameResult::Winner(winner) if winner == team => 1.0,
+				GameResult::Winner(_) => 0.0,
+				GameResult::Draw => 0.5,
+			};
+		}
+	}
+	score / (2 * games) as f32
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use super::*;
+
+	/// Player stacking tiles from the left, that resigns once it has played a
+	/// number of tiles. The more patient player always wins.
+	#[derive(Debug)]
+	struct PatientPlayer {
+		/// Number of own tiles after which the player resigns.
+		patience: usize,
+	}
+
+	impl Player for PatientPlayer {
+		fn make_move(&self, board: &Board, _me: Team) -> usize {
+			let (width, _) = board.dimensions();
+			(0..width).find(|column| board.is_legal_move(*column)).unwrap()
+		}
+
+		fn should_resign(&self, board: &Board, me: Team) -> bool {
+			board.field().iter().filter(|tile| **tile == Some(me)).count() >= self.patience
+		}
+	}
+
+	#[test]
+	fn improving_champions_win() {
+		let mut history = ChampionHistory::new(2, 1);
+		assert_eq!(history.push(PatientPlayer { patience: 1 }), None);
+		assert_eq!(history.push(PatientPlayer { patience: 2 }), None);
+		for patience in 3..8 {
+			let win_rate = history.push(PatientPlayer { patience }).unwrap();
+			assert!(win_rate > 0.5, "patience {patience}: win rate {win_rate}");
+		}
+		assert_eq!(history.champions.len(), 2);
+	}
+}
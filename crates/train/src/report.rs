@@ -0,0 +1,124 @@
+//! Reporting of training metrics, e.g. for plotting.
+
+use std::{
+	fs::{File, OpenOptions},
+	io::{BufWriter, Write},
+	path::Path,
+	time::Duration,
+};
+
+/// Metrics of a single training step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StepReport {
+	/// Number of the training step, starting at 0.
+	pub step: usize,
+	/// Score of the best model.
+	pub best_score: f32,
+	/// Mean score of the population.
+	pub mean_score: f32,
+	/// Validation score of the best model, if validation ran in this step.
+	pub validation_score: Option<f32>,
+	/// Mean distance of the model parameters to the population's mean
+	/// parameters.
+	pub diversity: f32,
+	/// Time the training step took.
+	pub elapsed: Duration,
+}
+
+/// Receiver of the metrics of each training step.
+pub trait TrainingReporter {
+	/// Called after each training step.
+	fn on_step(&mut self, report: &StepReport);
+}
+
+/// Training reporter appending the metrics as CSV rows to a file. Each row is
+/// flushed right away, so a crash doesn't lose data.
+#[derive(Debug)]
+pub struct CsvLogger {
+	/// Writer to the CSV file.
+	writer: BufWriter<File>,
+}
+
+impl CsvLogger {
+	/// CSV header row.
+	const HEADER: &'static str = "step,best_score,mean_score,validation_score,diversity,elapsed_ms";
+
+	/// Open the CSV file for appending, creating it if needed. The header is
+	/// written once, only if the file is empty.
+	pub fn create(path: impl AsRef<Path>) -> std::io::Result<Self> {
+		let file = OpenOptions::new().create(true).append(true).open(path)?;
+		let mut writer = BufWriter::new(file);
+		if writer.get_ref().metadata()?.len() == 0 {
+			writeln!(writer, "{}", Self::HEADER)?;
+			writer.flush()?;
+		}
+		Ok(Self { writer })
+	}
+
+	/// Write the report as CSV row. A missing validation score is left empty.
+	fn write_row(&mut self, report: &StepReport) -> std::io::Result<()> {
+		let validation_score =
+			report.validation_score.map(|score| score.to_string()).unwrap_or_default();
+		writeln!(
+			self.writer,
+			"{},{},{},{},{},{}",
+			report.step,
+			report.best_score,
+			report.mean_score,
+			validation_score,
+			report.diversity,
+			report.elapsed.as_millis()
+		)?;
+		self.writer.flush()
+	}
+}
+
+impl TrainingReporter for CsvLogger {
+	fn on_step(&mut self, report: &StepReport) {
+		self.write_row(report).expect("writing CSV row");
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use super::*;
+
+	#[test]
+	fn writes_header_and_rows() {
+		let path = std::env::temp_dir().join("connect-four-csv-logger-test.csv");
+		_ = std::fs::remove_file(&path);
+
+		let mut logger = CsvLogger::create(&path).unwrap();
+		for step in 0..3 {
+			logger.on_step(&StepReport {
+				step,
+				best_score: 1.5,
+				mean_score: 0.25,
+				validation_score: (step == 1).then_some(0.5),
+				diversity: 0.125,
+				elapsed: Duration::from_millis(42),
+			});
+		}
+		drop(logger);
+		// Appending doesn't repeat the header.
+		CsvLogger::create(&path).unwrap().on_step(&StepReport {
+			step: 3,
+			best_score: 2.0,
+			mean_score: 1.0,
+			validation_score: None,
+			diversity: 0.0,
+			elapsed: Duration::from_secs(1),
+		});
+
+		let content = std::fs::read_to_string(&path).unwrap();
+		std::fs::remove_file(&path).unwrap();
+		let lines = content.lines().collect::<Vec<_>>();
+		assert_eq!(lines.len(), 5);
+		assert_eq!(lines[0], "step,best_score,mean_score,validation_score,diversity,elapsed_ms");
+		assert_eq!(lines[1], "0,1.5,0.25,,0.125,42");
+		assert_eq!(lines[2], "1,1.5,0.25,0.5,0.125,42");
+		assert_eq!(lines[4], "3,2,1,,0,1000");
+	}
+}
@@ -0,0 +1,79 @@
+//! Lazy generation of self-play games, e.g. for online training.
+
+use game::{Board, Game, GameRecord, Player, Team};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+/// Number of random plies to start each self-play game with, so that
+/// deterministic players don't play the same game over and over.
+const OPENING_PLIES: usize = 2;
+
+/// Generate an endless stream of games between players `a` and `b`. Each game
+/// starts from a random opening, determined by the seed, and the players
+/// alternate colors, starting with `a` as [`Team::X`]. Games are only played
+/// when requested from the iterator. Player errors are converted to losses.
+pub fn self_play_stream<'a>(
+	a: &'a dyn Player,
+	b: &'a dyn Player,
+	seed: u64,
+) -> impl Iterator<Item = GameRecord> + 'a {
+	let mut rng = StdRng::seed_from_u64(seed);
+	(0_usize..).map(move |index| {
+		let mut start = Board::default();
+		let (width, _) = start.dimensions();
+		for ply in 0..OPENING_PLIES {
+			let team = if ply % 2 == 0 { Team::X } else { Team::O };
+			let columns =
+				(0..width).filter(|column| start.is_legal_move(*column)).collect::<Vec<_>>();
+			let column = *columns.choose(&mut rng).expect("No possible moves");
+			start.put_tile(column, team).expect("Possible move was in fact impossible");
+		}
+
+		let (player_x, player_o) = if index % 2 == 0 { (a, b) } else { (b, a) };
+		let mut game = Game::builder().player_x(player_x).player_o(player_o).board(start).build();
+		let result = game.run_error_loss();
+		GameRecord { start, moves: game.history().to_vec(), result }
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use super::*;
+
+	/// Player playing the first legal column, searching from the left or from
+	/// the right.
+	#[derive(Debug)]
+	struct EdgePlayer {
+		/// Whether to search from the left.
+		left: bool,
+	}
+
+	impl Player for EdgePlayer {
+		fn make_move(&self, board: &Board, _me: Team) -> usize {
+			let (width, _) = board.dimensions();
+			let mut columns = (0..width).filter(|column| board.is_legal_move(*column));
+			if self.left {
+				columns.next().unwrap()
+			} else {
+				columns.next_back().unwrap()
+			}
+		}
+	}
+
+	#[test]
+	fn colors_alternate() {
+		let (a, b) = (EdgePlayer { left: true }, EdgePlayer { left: false });
+		let games = self_play_stream(&a, &b, 42).take(4).collect::<Vec<_>>();
+		assert_eq!(games.len(), 4);
+		for (index, game) in games.iter().enumerate() {
+			assert_eq!(game.start.field().iter().filter(|tile| tile.is_some()).count(), 2);
+			// After the opening, X makes the first move.
+			let expected = if index % 2 == 0 { 0 } else { 6 };
+			assert_eq!(game.moves[0], (Team::X, expected));
+		}
+
+		let again = self_play_stream(&a, &b, 42).take(4).collect::<Vec<_>>();
+		assert_eq!(games, again);
+	}
+}
@@ -0,0 +1,296 @@
+//! Data-driven training of the value network on the outcomes of self-play
+//! games. Each iteration generates fresh self-play games with the current
+//! model, labels the positions with the game outcomes and fits the value
+//! network to them.
+
+use std::{fmt::Debug, sync::Mutex};
+
+use burn::tensor::backend::Backend;
+use game::{Board, GameRecord, GameResult, Player, Team};
+use players::AiValuePlayer;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{evaluation::Evaluator, optimizers::Optimizer, self_play::self_play_stream, EsTrainer};
+
+/// Position labeled with the outcome of the game it was played in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ValueSample {
+	/// The position.
+	pub board: Board,
+	/// The team the value is from the view of.
+	pub me: Team,
+	/// The target value: 1 if `me` won the game, -1 if it lost and 0 for a
+	/// draw.
+	pub target: f32,
+}
+
+/// Build the value dataset from the game records. Every position after a move
+/// is labeled from the view of the team that made the move, which is how the
+/// value network evaluates the leaves of its search.
+#[must_use]
+pub fn value_samples(records: &[GameRecord]) -> Vec<ValueSample> {
+	let mut samples = Vec::new();
+	for record in records {
+		let mut board = record.start;
+		for (team, column) in &record.moves {
+			if board.put_tile(*column, *team).is_err() {
+				break;
+			}
+			let target = match record.result {
+				GameResult::Draw => 0.0,
+				GameResult::Winner(winner) if winner == *team => 1.0,
+				GameResult::Winner(_) => -1.0,
+			};
+			samples.push(ValueSample { board, me: *team, target });
+		}
+	}
+	samples
+}
+
+/// Evaluator scoring value networks by the negative mean squared error of
+/// their predictions on a dataset. The targets are in `[-1, 1]`, so the
+/// models are expected to have an output scale of 1.
+#[derive(Debug, Clone, Default)]
+pub struct ValueDatasetEvaluator {
+	/// The dataset to evaluate on.
+	samples: Vec<ValueSample>,
+}
+
+impl ValueDatasetEvaluator {
+	/// Create a new evaluator on the dataset.
+	#[must_use]
+	pub fn new(samples: Vec<ValueSample>) -> Self {
+		Self { samples }
+	}
+
+	/// Get the dataset.
+	#[must_use]
+	pub fn samples(&self) -> &[ValueSample] {
+		&self.samples
+	}
+
+	/// Replace the dataset.
+	pub fn set_samples(&mut self, samples: Vec<ValueSample>) -> &mut Self {
+		self.samples = samples;
+		self
+	}
+
+	/// Compute the mean squared error of the model's predictions on the
+	/// dataset. The positions are evaluated in one batch per team.
+	pub fn loss<B: Backend>(&self, model: &AiValuePlayer<B>) -> f32 {
+		if self.samples.is_empty() {
+			return 0.0;
+		}
+
+		let mut error = 0.0;
+		for team in Team::all() {
+			let (boards, targets): (Vec<_>, Vec<_>) = self
+				.samples
+				.iter()
+				.filter(|sample| sample.me == team)
+				.map(|sample| (sample.board, sample.target))
+				.unzip();
+			for (value, target) in model.evaluate_batch(&boards, team).into_iter().zip(targets) {
+				let diff = value as f32 - target;
+				error += diff * diff;
+			}
+		}
+		error / self.samples.len() as f32
+	}
+}
+
+impl<B: Backend> Evaluator<AiValuePlayer<B>> for ValueDatasetEvaluator {
+	fn evaluate(&mut self, models: &[AiValuePlayer<B>]) -> Vec<f32> {
+		models.iter().map(|model| -self.loss(model)).collect()
+	}
+}
+
+/// Player wrapper for exploration, sampling the moves with probabilities
+/// following the softmax of the inner player's move scores divided by the
+/// temperature. Plays the inner player's move at a temperature of 0 or if the
+/// inner player doesn't score its moves.
+#[derive(Debug)]
+pub struct TemperaturePlayer<P> {
+	/// The player to explore around.
+	inner: P,
+	/// The softmax temperature.
+	temperature: f64,
+	/// Random number generator to sample the moves.
+	rng: Mutex<StdRng>,
+}
+
+impl<P: Player> TemperaturePlayer<P> {
+	/// Create a new exploring player with the temperature and the seed for
+	/// sampling the moves.
+	#[must_use]
+	pub fn new(inner: P, temperature: f64, seed: u64) -> Self {
+		Self { inner, temperature, rng: Mutex::new(StdRng::seed_from_u64(seed)) }
+	}
+}
+
+impl<P: Player> Player for TemperaturePlayer<P> {
+	fn make_move(&self, board: &Board, me: Team) -> usize {
+		if self.temperature <= 0.0 {
+			return self.inner.make_move(board, me);
+		}
+		let Some(scores) = self.inner.scored_moves(board, me) else {
+			return self.inner.make_move(board, me);
+		};
+
+		// Shift by the best score, so that wins scored `f64::MAX` don't overflow.
+		let best = scores
+			.iter()
+			.map(|(_, score)| *score)
+			.filter(|score| !score.is_nan())
+			.fold(f64::NEG_INFINITY, f64::max);
+		let weights = scores
+			.iter()
+			.map(|(column, score)| {
+				let weight = ((score - best) / self.temperature).exp();
+				(*column, if weight.is_nan() { 0.0 } else { weight })
+			})
+			.collect::<Vec<_>>();
+		let total = weights.iter().map(|(_, weight)| weight).sum::<f64>();
+		if total <= 0.0 {
+			return self.inner.make_move(board, me);
+		}
+
+		let mut pick = self.rng.lock().expect("lock poisened").gen::<f64>() * total;
+		for (column, weight) in &weights {
+			if pick < *weight {
+				return *column;
+			}
+			pick -= weight;
+		}
+		weights.iter().rev().find(|(_, weight)| *weight > 0.0).map_or(0, |(column, _)| *column)
+	}
+
+	fn new_game(&self) {
+		self.inner.new_game();
+	}
+
+	fn name(&self) -> &str {
+		self.inner.name()
+	}
+}
+
+/// Trainer fitting the value network to the outcomes of self-play games. Each
+/// iteration plays a number of self-play games with the current model,
+/// exploring via [`TemperaturePlayer`]s, and fits the model to the resulting
+/// dataset with evolution strategies.
+#[derive(Debug, typed_builder::TypedBuilder)]
+pub struct SelfPlayTrainer<B, Opt>
+where
+	B: Backend + Debug,
+	Opt: Optimizer<B> + Debug,
+{
+	/// Evolution strategies trainer fitting the model to the dataset.
+	trainer: EsTrainer<B, AiValuePlayer<B>, ValueDatasetEvaluator, Opt>,
+	/// Number of self-play games per iteration.
+	games: usize,
+	/// Exploration temperature of the self-play games.
+	#[builder(default = 1.0)]
+	temperature: f64,
+	/// Number of training steps fitting the model per iteration.
+	#[builder(default = 10)]
+	fit_steps: usize,
+	/// Random number generator for the seeds of the games and training steps.
+	#[builder(setter(skip), default = StdRng::from_entropy())]
+	rng: StdRng,
+}
+
+impl<B, Opt> SelfPlayTrainer<B, Opt>
+where
+	B: Backend + Debug,
+	Opt: Optimizer<B> + Debug,
+{
+	/// Seed the random number generator of the trainer.
+	#[must_use]
+	pub fn with_seed(mut self, seed: u64) -> Self {
+		self.rng = StdRng::seed_from_u64(seed);
+		self
+	}
+
+	/// Get the model.
+	pub fn model(&self) -> &AiValuePlayer<B> {
+		self.trainer.model()
+	}
+
+	/// Get the inner evolution strategies trainer.
+	pub fn trainer(&self) -> &EsTrainer<B, AiValuePlayer<B>, ValueDatasetEvaluator, Opt> {
+		&self.trainer
+	}
+
+	/// Play the self-play games of one iteration with the current model.
+	pub fn generate_games(&mut self) -> Vec<GameRecord> {
+		let seeds: [u64; 3] = self.rng.gen();
+		let model = self.trainer.model();
+		let a = TemperaturePlayer::new(model, self.temperature, seeds[0]);
+		let b = TemperaturePlayer::new(model, self.temperature, seeds[1]);
+		self_play_stream(&a, &b, seeds[2]).take(self.games).collect()
+	}
+
+	/// Fit the model to the dataset.
+	pub fn fit(&mut self, samples: Vec<ValueSample>) -> &mut Self {
+		self.trainer.evaluator_mut().set_samples(samples);
+		for _ in 0..self.fit_steps {
+			let seed = self.rng.gen();
+			self.trainer.train_step_with_seed(seed);
+		}
+		self
+	}
+
+	/// Train for one iteration: play the self-play games, build the dataset
+	/// and fit the model to it. Returns the size of the dataset.
+	pub fn train_iteration(&mut self) -> usize {
+		let records = self.generate_games();
+		let samples = value_samples(&records);
+		let size = samples.len();
+		self.fit(samples);
+		size
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use burn::module::Module;
+	use players::NdArrayBackend;
+
+	use super::*;
+	use crate::{optimizers::Sgd, utils::FlattenVisitor};
+
+	/// Model type used in the tests.
+	type TestModel = AiValuePlayer<NdArrayBackend>;
+
+	#[test]
+	fn iteration_builds_dataset_and_updates_model() {
+		let optimizer = Sgd::<NdArrayBackend>::builder().learning_rate(0.1).momentum(0.9).build();
+		let es = EsTrainer::builder()
+			.model(TestModel::init(1))
+			.evaluator(ValueDatasetEvaluator::default())
+			.optimizer(optimizer)
+			.samples(2)
+			.std(0.1)
+			.build();
+		let mut trainer =
+			SelfPlayTrainer::builder().trainer(es).games(3).fit_steps(1).build().with_seed(3);
+		let parameters = |model: &TestModel| {
+			let mut visitor = FlattenVisitor { parameters: None };
+			model.visit(&mut visitor);
+			visitor.parameters.unwrap().into_data().value
+		};
+		let before = parameters(trainer.model());
+
+		let records = trainer.generate_games();
+		assert_eq!(records.len(), 3);
+		let samples = value_samples(&records);
+		let moves = records.iter().map(|record| record.moves.len()).sum::<usize>();
+		assert_eq!(samples.len(), moves);
+
+		trainer.fit(samples);
+		assert_eq!(trainer.trainer().evaluator().samples().len(), moves);
+		assert_ne!(parameters(trainer.model()), before);
+	}
+}